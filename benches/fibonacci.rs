@@ -0,0 +1,123 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use fib_fhe::run::{strategy_recommendation, Timings};
+use fib_fhe::serialization::encrypted_table_bytes;
+use fib_fhe::{
+    build_encrypted_fibs, build_encrypted_indices, build_encrypted_indices_with_client,
+    fibonacci_additions, fibonacci_lookup_scalar_index, fibonacci_lookup_with_tables, with_server_key, FheUint16,
+    PublicKey, MAX_FIBONACCI_INDEX,
+};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use tfhe::prelude::*;
+use tfhe::{generate_keys, ClientKey, ConfigBuilder, ServerKey};
+
+/// Fixed encrypted input used by every benchmark.
+const BENCH_INDEX: u16 = 12;
+
+/// Keys and tables shared by every benchmark of the process, so that key
+/// generation, which takes far longer than any measured operation, happens once
+/// and never within a benchmark.
+struct Fixture {
+    client_key: ClientKey,
+    server_key: ServerKey,
+    pks: PublicKey,
+    encrypted_indices: Vec<FheUint16>,
+    encrypted_fibs: Vec<FheUint16>,
+}
+
+fn fixture() -> &'static Fixture {
+    static FIXTURE: OnceLock<Fixture> = OnceLock::new();
+    FIXTURE.get_or_init(|| {
+        let (client_key, server_key) = generate_keys(ConfigBuilder::default().build());
+        let pks = PublicKey::new(&client_key);
+        let encrypted_indices = build_encrypted_indices(&pks);
+        let encrypted_fibs = build_encrypted_fibs(&pks);
+        Fixture { client_key, server_key, pks, encrypted_indices, encrypted_fibs }
+    })
+}
+
+/// The server key is thread-local: each benchmark runs on criterion's thread
+/// inside [`with_server_key`], so it is set for exactly that benchmark.
+fn bench_strategies(c: &mut Criterion) {
+    let Fixture { client_key, server_key, pks, encrypted_indices, encrypted_fibs } = fixture();
+    with_server_key(server_key.clone(), || {
+        let n = FheUint16::encrypt(BENCH_INDEX, client_key);
+
+        let mut group = c.benchmark_group("fibonacci");
+
+        group.bench_function("setup", |b| {
+            b.iter(|| (build_encrypted_indices(pks), build_encrypted_fibs(pks)))
+        });
+
+        group.bench_function("additions", |b| {
+            b.iter(|| fibonacci_additions(black_box(&n), pks))
+        });
+
+        group.bench_function("lookup", |b| {
+            b.iter(|| fibonacci_lookup_with_tables(black_box(&n), encrypted_indices, encrypted_fibs))
+        });
+
+        group.bench_function("lookup_scalar_index", |b| {
+            b.iter(|| fibonacci_lookup_scalar_index(black_box(&n), encrypted_fibs))
+        });
+
+        group.finish();
+    });
+}
+
+/// Additions and lookup for increasing encrypted `n`, then the faster one for
+/// [`BENCH_INDEX`] as [`strategy_recommendation`] reports it.
+fn bench_by_index(c: &mut Criterion) {
+    let Fixture { client_key, server_key, pks, encrypted_indices, encrypted_fibs } = fixture();
+    with_server_key(server_key.clone(), || {
+        let mut group = c.benchmark_group("by_index");
+        for index in [0u16, BENCH_INDEX, MAX_FIBONACCI_INDEX] {
+            let n = FheUint16::encrypt(index, client_key);
+            group.bench_with_input(BenchmarkId::new("additions", index), &n, |b, n| {
+                b.iter(|| fibonacci_additions(black_box(n), pks))
+            });
+            group.bench_with_input(BenchmarkId::new("lookup", index), &n, |b, n| {
+                b.iter(|| fibonacci_lookup_with_tables(black_box(n), encrypted_indices, encrypted_fibs))
+            });
+        }
+        group.finish();
+
+        let n = FheUint16::encrypt(BENCH_INDEX, client_key);
+        let t_additions = Instant::now();
+        fibonacci_additions(&n, pks);
+        let additions = t_additions.elapsed();
+        let t_lookup = Instant::now();
+        fibonacci_lookup_with_tables(&n, encrypted_indices, encrypted_fibs);
+        let lookup = t_lookup.elapsed();
+        let timings = Timings { setup: None, additions: Some(additions), lookup: Some(lookup), matrix: None };
+        println!("recommended strategy: {}", strategy_recommendation(&timings));
+    });
+}
+
+/// Public-key vs client-key encryption of the index table: time, and the
+/// serialized size printed once before measuring. Encryption needs no server key.
+fn bench_index_encryption(c: &mut Criterion) {
+    let Fixture { client_key, pks, encrypted_indices, .. } = fixture();
+
+    println!(
+        "index table: {} bytes with the public key, {} bytes with the client key",
+        encrypted_table_bytes(encrypted_indices),
+        encrypted_table_bytes(&build_encrypted_indices_with_client(client_key)),
+    );
+
+    let mut group = c.benchmark_group("index_encryption");
+
+    group.bench_function("public_key", |b| b.iter(|| build_encrypted_indices(pks)));
+
+    group.bench_function("client_key", |b| b.iter(|| build_encrypted_indices_with_client(client_key)));
+
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    // Each iteration takes seconds, so keep the sample count at criterion's minimum.
+    config = Criterion::default().sample_size(10).measurement_time(Duration::from_secs(60));
+    targets = bench_strategies, bench_by_index, bench_index_encryption
+}
+criterion_main!(benches);
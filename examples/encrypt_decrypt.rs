@@ -0,0 +1,35 @@
+//! The minimal client/server flow, end to end:
+//!
+//! ```bash
+//! cargo run --release --example encrypt_decrypt
+//! ```
+
+use fib_fhe::client::decrypt_value;
+use fib_fhe::config::FibConfig;
+use fib_fhe::keys::generate_keys_with;
+use fib_fhe::{
+    build_encrypted_fibs, build_encrypted_indices, fibonacci_lookup_with_tables, fibonacci_plaintext, with_server_key,
+    FheUint16,
+};
+use tfhe::prelude::*;
+
+fn main() {
+    // Client-side: the config, then the client, server and public keys.
+    let (client_key, server_key, public_key) = generate_keys_with(FibConfig::default());
+
+    // Client-side: encrypt the index with the public key.
+    let n = 12u16;
+    let encrypted_n = FheUint16::encrypt(n, &public_key);
+
+    // Server-side: the tables need only the public key, the lookup the server key.
+    let encrypted_result = with_server_key(server_key, || {
+        let encrypted_indices = build_encrypted_indices(&public_key);
+        let encrypted_fibs = build_encrypted_fibs(&public_key);
+        fibonacci_lookup_with_tables(&encrypted_n, &encrypted_indices, &encrypted_fibs)
+    });
+
+    // Client-side: decrypt with the client key.
+    let result: u16 = decrypt_value(&encrypted_result, &client_key);
+    assert_eq!(result, fibonacci_plaintext(n));
+    println!("F({}) = {}", n, result);
+}
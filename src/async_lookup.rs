@@ -0,0 +1,53 @@
+//! Lookup from async code, with the `async` feature.
+//!
+//! A lookup takes seconds of CPU time, which would stall every other task of a
+//! tokio executor thread. [`fibonacci_lookup_async`] runs it on tokio's
+//! blocking pool instead. The server key is thread-local, so it is moved into
+//! the blocking task and scoped there with
+//! [`with_server_key`](crate::with_server_key); the key set on the awaiting
+//! thread, if any, is not seen by the task.
+
+use crate::{fibonacci_lookup_with_tables, with_server_key, FheUint16, ServerKey};
+use std::sync::Arc;
+
+/// [`fibonacci_lookup_with_tables`] on tokio's blocking pool.
+///
+/// The tables are shared with the task through `Arc`s, so a server can build
+/// them once and hand clones to every request. Must be awaited inside a tokio
+/// runtime.
+///
+/// # Panics
+///
+/// Resumes the panic of the lookup, if any.
+pub async fn fibonacci_lookup_async(
+    n: FheUint16,
+    encrypted_indices: Arc<[FheUint16]>,
+    encrypted_fibs: Arc<[FheUint16]>,
+    server_key: ServerKey,
+) -> FheUint16 {
+    let task = tokio::task::spawn_blocking(move || {
+        with_server_key(server_key, || fibonacci_lookup_with_tables(&n, &encrypted_indices, &encrypted_fibs))
+    });
+    match task.await {
+        Ok(result) => result,
+        Err(error) => std::panic::resume_unwind(error.into_panic()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestHarness;
+
+    #[tokio::test]
+    async fn test_fibonacci_lookup_async() {
+        let harness = TestHarness::new();
+        let encrypted_indices: Arc<[FheUint16]> = harness.indices().into();
+        let encrypted_fibs: Arc<[FheUint16]> = harness.fibs().into();
+        let n = harness.encrypt(15);
+
+        let result = fibonacci_lookup_async(n, encrypted_indices, encrypted_fibs, harness.server_key().clone()).await;
+        let decrypted: u16 = harness.decrypt(&result);
+        assert_eq!(decrypted, 610);
+    }
+}
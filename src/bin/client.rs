@@ -0,0 +1,80 @@
+//! Client side of the client/server split: holds the client key, encrypts the
+//! index and decrypts the result. Ciphertexts go through stdin/stdout.
+//!
+//! ```bash
+//! client [--keys <dir>] encrypt <n> > ct.bin
+//! client [--keys <dir>] decrypt < res.bin
+//! ```
+
+use fib_fhe::client::{decrypt_result, encrypt_index};
+use fib_fhe::config::FibConfig;
+use fib_fhe::keys::load_or_generate_keys;
+use fib_fhe::MAX_FIBONACCI_INDEX;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: client [--keys <dir>] encrypt <n> | decrypt";
+
+enum Command {
+    Encrypt(u16),
+    Decrypt,
+}
+
+fn parse_args() -> Result<(PathBuf, Command), String> {
+    let mut keys_dir = PathBuf::from("keys");
+    let mut command = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--keys" => keys_dir = args.next().ok_or("--keys needs a directory")?.into(),
+            "encrypt" => {
+                let n = args.next().ok_or("encrypt needs an index")?;
+                let n = n
+                    .parse::<u16>()
+                    .ok()
+                    .filter(|n| *n <= MAX_FIBONACCI_INDEX)
+                    .ok_or(format!("index must be a number between 0 and {}", MAX_FIBONACCI_INDEX))?;
+                command = Some(Command::Encrypt(n));
+            }
+            "decrypt" => command = Some(Command::Decrypt),
+            other => return Err(format!("unexpected argument `{}`", other)),
+        }
+    }
+    Ok((keys_dir, command.ok_or(USAGE)?))
+}
+
+fn run(keys_dir: PathBuf, command: Command) -> io::Result<()> {
+    // Generates and saves the keys on first use so the server can pick them up.
+    let (client_key, _, pks) = load_or_generate_keys(&keys_dir, FibConfig::default())?;
+    match command {
+        Command::Encrypt(n) => {
+            let mut stdout = io::stdout().lock();
+            stdout.write_all(&encrypt_index(n, &pks))?;
+            stdout.flush()
+        }
+        Command::Decrypt => {
+            let mut bytes = Vec::new();
+            io::stdin().lock().read_to_end(&mut bytes)?;
+            println!("{}", decrypt_result(&bytes, &client_key)?);
+            Ok(())
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let (keys_dir, command) = match parse_args() {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+    match run(keys_dir, command) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("client: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,51 @@
+//! Server side of the client/server split: only ever loads the server and public
+//! keys, reads an encrypted index on stdin and writes the encrypted `F(n)` on
+//! stdout.
+//!
+//! ```bash
+//! server [--keys <dir>] < ct.bin > res.bin
+//! ```
+
+use fib_fhe::keys::load_server_keys;
+use fib_fhe::server::serve_lookup;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: server [--keys <dir>] < ct.bin > res.bin";
+
+fn parse_args() -> Result<PathBuf, String> {
+    let mut keys_dir = PathBuf::from("keys");
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--keys" => keys_dir = args.next().ok_or("--keys needs a directory")?.into(),
+            other => return Err(format!("unexpected argument `{}`", other)),
+        }
+    }
+    Ok(keys_dir)
+}
+
+fn run(keys_dir: PathBuf) -> io::Result<()> {
+    let (server_key, pks) = load_server_keys(&keys_dir)?;
+    let mut stdout = io::stdout().lock();
+    serve_lookup(io::stdin().lock(), &mut stdout, server_key, &pks)?;
+    stdout.flush()
+}
+
+fn main() -> ExitCode {
+    let keys_dir = match parse_args() {
+        Ok(dir) => dir,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+    match run(keys_dir) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("server: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,59 @@
+//! Acceptance sweep: computes `F(n)` for every `n` in `0..=MAX_FIBONACCI_INDEX`
+//! with every strategy of `fib_fhe::STRATEGIES` and exits non-zero on the first
+//! result that differs from the plaintext reference. Meant to be run after
+//! changing parameters.
+//!
+//! ```bash
+//! verify [--params default|fast]
+//! ```
+
+use fib_fhe::config::{FibConfig, ParamSet};
+use fib_fhe::keys::generate_keys_with;
+use fib_fhe::verify::check_strategies_with_tables;
+use fib_fhe::{build_encrypted_fibs, build_encrypted_indices, fibonacci_plaintext, MAX_FIBONACCI_INDEX};
+use std::process::ExitCode;
+use tfhe::set_server_key;
+
+const USAGE: &str = "usage: verify [--params default|fast]";
+
+fn parse_args() -> Result<FibConfig, String> {
+    let mut config = FibConfig::default();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--params" => {
+                config.params = match args.next().ok_or("--params needs a value")?.as_str() {
+                    "default" => ParamSet::Default,
+                    "fast" => ParamSet::Fast,
+                    other => return Err(format!("unknown parameter set `{}`", other)),
+                };
+            }
+            other => return Err(format!("unexpected argument `{}`", other)),
+        }
+    }
+    Ok(config)
+}
+
+fn main() -> ExitCode {
+    let config = match parse_args() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let (client_key, server_key, pks) = generate_keys_with(config);
+    set_server_key(server_key);
+    let encrypted_indices = build_encrypted_indices(&pks);
+    let encrypted_fibs = build_encrypted_fibs(&pks);
+
+    for n in 0..=MAX_FIBONACCI_INDEX {
+        if let Err(mismatch) = check_strategies_with_tables(n, &client_key, &pks, &encrypted_indices, &encrypted_fibs) {
+            eprintln!("verify: {}", mismatch);
+            return ExitCode::FAILURE;
+        }
+        println!("F({}) = {}: ok", n, fibonacci_plaintext(n));
+    }
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,714 @@
+//! Command-line arguments of the `fib-fhe` binary.
+
+use crate::config::{FibConfig, ParamSet};
+use crate::sequence::{max_index_u8, Fibonacci, Lucas, Pell, Sequence, Tribonacci};
+use crate::negafibonacci::MAX_NEGAFIBONACCI_INDEX;
+use crate::run::Selection;
+use crate::MAX_FIBONACCI_INDEX;
+use std::path::PathBuf;
+
+pub use crate::StrategyChoice;
+
+pub const USAGE: &str = "usage: fib-fhe keygen [--params default|fast] [--multibit] [--format text|json]
+       fib-fhe [--keys <dir>] [--compressed-keys] [--tables <dir>]
+               [--params default|fast] [--multibit] [--seed <n>] [--strategy additions|lookup|matrix|both|all]
+               [--sequence fib|lucas|pell|tribonacci|negafib] [--u8]
+               [--format text|json] [--skip-additions] [--skip-lookup] [--batch <n,n,...>]
+               [--threads <n>] [--trivial] [--ciphertext-stdin] [--plaintext] [--estimate] [--log-csv <path>]
+               [--radix dec|hex|bin] [--max-retries <n>] [n]";
+
+/// Which sequence `main` computes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SequenceChoice {
+    #[default]
+    Fibonacci,
+    /// Lucas numbers, computed with the lookup strategy only.
+    Lucas,
+    /// Pell numbers, computed with the lookup strategy only.
+    Pell,
+    /// Tribonacci numbers, computed with the lookup strategy only.
+    Tribonacci,
+    /// Fibonacci extended to negative indices, computed with the lookup strategy
+    /// only; the index is read from [`Args::signed_n`].
+    Negafibonacci,
+}
+
+impl SequenceChoice {
+    pub fn from_name(name: &str) -> Option<Self> {
+        [Self::Fibonacci, Self::Lucas, Self::Pell, Self::Tribonacci, Self::Negafibonacci]
+            .into_iter()
+            .find(|choice| choice.name() == name)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Fibonacci => Fibonacci::NAME,
+            Self::Lucas => Lucas::NAME,
+            Self::Pell => Pell::NAME,
+            Self::Tribonacci => Tribonacci::NAME,
+            Self::Negafibonacci => "negafib",
+        }
+    }
+
+    pub fn max_index(self) -> u16 {
+        match self {
+            Self::Fibonacci => Fibonacci::MAX_INDEX,
+            Self::Lucas => Lucas::MAX_INDEX,
+            Self::Pell => Pell::MAX_INDEX,
+            Self::Tribonacci => Tribonacci::MAX_INDEX,
+            Self::Negafibonacci => MAX_NEGAFIBONACCI_INDEX.unsigned_abs(),
+        }
+    }
+
+    /// Largest index whose value fits in `u8`, for the sequences supporting
+    /// `--u8`.
+    ///
+    /// Only the lookup has an 8-bit table: `fib --u8` is restricted to it, and
+    /// negafibonacci values are signed.
+    pub fn max_index_u8(self) -> Option<u16> {
+        match self {
+            Self::Fibonacci => Some(max_index_u8::<Fibonacci>()),
+            Self::Lucas => Some(max_index_u8::<Lucas>()),
+            Self::Pell => Some(max_index_u8::<Pell>()),
+            Self::Tribonacci => Some(max_index_u8::<Tribonacci>()),
+            Self::Negafibonacci => None,
+        }
+    }
+}
+
+/// How `main` prints its results.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    /// A single JSON object on stdout.
+    Json,
+}
+
+/// How `main` writes a decrypted result in text mode.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Radix {
+    #[default]
+    Dec,
+    /// `0x`-prefixed, lowercase.
+    Hex,
+    /// `0b`-prefixed, to inspect bit patterns.
+    Bin,
+}
+
+impl Radix {
+    /// `value` written in this radix, e.g. `55`, `0x37` or `0b110111`.
+    pub fn format(self, value: u16) -> String {
+        match self {
+            Self::Dec => value.to_string(),
+            Self::Hex => format!("{:#x}", value),
+            Self::Bin => format!("{:#b}", value),
+        }
+    }
+}
+
+/// Invalid inputs in a row after which the interactive prompt gives up, without
+/// `--max-retries`.
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Args {
+    /// Index to compute; prompt interactively when `None`.
+    pub n: Option<u16>,
+    /// Signed index for `--sequence negafib`, which takes it instead of `n`.
+    pub signed_n: Option<i16>,
+    pub strategy: StrategyChoice,
+    pub sequence: SequenceChoice,
+    pub format: OutputFormat,
+    /// Radix of the results printed in text mode.
+    pub radix: Radix,
+    pub keys: Option<PathBuf>,
+    /// Directory caching the encrypted lookup tables.
+    pub tables: Option<PathBuf>,
+    /// Generate and store the server key in compressed form.
+    pub compressed_keys: bool,
+    /// Parameter set used when generating keys.
+    pub params: ParamSet,
+    /// Generate keys with the multi-bit variant of `params`.
+    pub multibit: bool,
+    /// Indices to compute in one session with `--batch`.
+    pub batch: Option<Vec<u16>>,
+    /// Size of the rayon thread pool; the global pool when `None`.
+    pub threads: Option<usize>,
+    /// Build the lookup tables from trivial ciphertexts; insecure, test-only.
+    pub trivial: bool,
+    /// Serve one lookup: encrypted index on stdin, encrypted result on stdout,
+    /// with only the server keys of `--keys` loaded.
+    pub ciphertext_stdin: bool,
+    /// Seed for deterministic key generation; insecure, test-only.
+    pub seed: Option<u128>,
+    /// Leave the additions strategy out of `strategy`.
+    pub skip_additions: bool,
+    /// Leave the lookup strategy out of `strategy`.
+    pub skip_lookup: bool,
+    /// Look up an 8-bit table; narrows the index range to values below 256.
+    pub u8_output: bool,
+    /// Print the plaintext reference only, without keys, encryption or tables.
+    pub plaintext: bool,
+    /// Print the estimated operation counts of the selected strategies and
+    /// compute nothing.
+    pub estimate: bool,
+    /// `keygen` subcommand: time key generation and report the key sizes only.
+    pub keygen: bool,
+    /// CSV file to append one row per query to.
+    pub log_csv: Option<PathBuf>,
+    /// Invalid inputs in a row after which the interactive prompt gives up;
+    /// [`DEFAULT_MAX_RETRIES`] when `None`.
+    pub max_retries: Option<u32>,
+}
+
+impl Args {
+    /// Whether the additions strategy runs: selected and not skipped.
+    pub fn runs_additions(&self) -> bool {
+        self.strategy.additions() && !self.skip_additions
+    }
+
+    /// Whether the lookup strategy runs: selected and not skipped.
+    pub fn runs_lookup(&self) -> bool {
+        self.strategy.lookup() && !self.skip_lookup
+    }
+
+    pub fn runs_matrix(&self) -> bool {
+        self.strategy.matrix()
+    }
+
+    /// The strategies to run for a Fibonacci query.
+    pub fn selection(&self) -> Selection {
+        Selection { additions: self.runs_additions(), lookup: self.runs_lookup(), matrix: self.runs_matrix() }
+    }
+
+    /// Configuration of the keys to generate.
+    pub fn config(&self) -> FibConfig {
+        FibConfig { params: self.params, multibit: self.multibit }
+    }
+
+    /// Invalid inputs in a row after which the interactive prompt gives up.
+    pub fn retry_limit(&self) -> u32 {
+        self.max_retries.unwrap_or(DEFAULT_MAX_RETRIES)
+    }
+
+    /// Largest index accepted for the selected sequence and output width.
+    pub fn max_index(&self) -> u16 {
+        match self.sequence.max_index_u8() {
+            Some(max_index) if self.u8_output => max_index,
+            _ => self.sequence.max_index(),
+        }
+    }
+}
+
+/// Parse the arguments following the program name.
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+    let mut parsed = Args::default();
+    let mut index = None;
+    let mut flags = Vec::new();
+    let mut args = args.into_iter().peekable();
+    if args.next_if(|arg| arg == "keygen").is_some() {
+        parsed.keygen = true;
+    }
+    while let Some(arg) = args.next() {
+        if arg.starts_with("--") {
+            flags.push(arg.clone());
+        }
+        match arg.as_str() {
+            "--keys" => parsed.keys = Some(args.next().ok_or("--keys needs a directory")?.into()),
+            "--tables" => parsed.tables = Some(args.next().ok_or("--tables needs a directory")?.into()),
+            "--log-csv" => parsed.log_csv = Some(args.next().ok_or("--log-csv needs a file")?.into()),
+            "--compressed-keys" => parsed.compressed_keys = true,
+            "--trivial" => parsed.trivial = true,
+            "--ciphertext-stdin" => parsed.ciphertext_stdin = true,
+            "--skip-additions" => parsed.skip_additions = true,
+            "--skip-lookup" => parsed.skip_lookup = true,
+            "--u8" => parsed.u8_output = true,
+            "--plaintext" => parsed.plaintext = true,
+            "--estimate" => parsed.estimate = true,
+            "--multibit" => parsed.multibit = true,
+            "--params" => {
+                let value = args.next().ok_or("--params needs a value")?;
+                parsed.params = match value.as_str() {
+                    "default" => ParamSet::Default,
+                    "fast" => ParamSet::Fast,
+                    other => return Err(format!("unknown parameter set `{}`", other)),
+                };
+            }
+            "--strategy" => {
+                let value = args.next().ok_or("--strategy needs a value")?;
+                parsed.strategy = match value.as_str() {
+                    "additions" => StrategyChoice::Additions,
+                    "lookup" => StrategyChoice::Lookup,
+                    "matrix" => StrategyChoice::Matrix,
+                    "both" => StrategyChoice::Both,
+                    "all" => StrategyChoice::All,
+                    other => return Err(format!("unknown strategy `{}`", other)),
+                };
+            }
+            "--sequence" => {
+                let value = args.next().ok_or("--sequence needs a value")?;
+                parsed.sequence = SequenceChoice::from_name(&value)
+                    .ok_or(format!("unknown sequence `{}`", value))?;
+            }
+            "--format" => {
+                let value = args.next().ok_or("--format needs a value")?;
+                parsed.format = match value.as_str() {
+                    "text" => OutputFormat::Text,
+                    "json" => OutputFormat::Json,
+                    other => return Err(format!("unknown format `{}`", other)),
+                };
+            }
+            "--radix" => {
+                let value = args.next().ok_or("--radix needs a value")?;
+                parsed.radix = match value.as_str() {
+                    "dec" => Radix::Dec,
+                    "hex" => Radix::Hex,
+                    "bin" => Radix::Bin,
+                    other => return Err(format!("unknown radix `{}`", other)),
+                };
+            }
+            "--batch" => {
+                let value = args.next().ok_or("--batch needs a comma-separated list of indices")?;
+                let batch = value.split(',').map(|n| parse_index(n.trim())).collect::<Result<_, _>>()?;
+                parsed.batch = Some(batch);
+            }
+            "--seed" => {
+                let value = args.next().ok_or("--seed needs a number")?;
+                let seed = value.parse::<u128>().map_err(|_| format!("invalid seed `{}`: expected a number", value))?;
+                parsed.seed = Some(seed);
+            }
+            "--threads" => {
+                let value = args.next().ok_or("--threads needs a number")?;
+                let threads = value
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|threads| *threads > 0)
+                    .ok_or(format!("invalid thread count `{}`: expected a positive number", value))?;
+                parsed.threads = Some(threads);
+            }
+            "--max-retries" => {
+                let value = args.next().ok_or("--max-retries needs a number")?;
+                let max_retries = value
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|max_retries| *max_retries > 0)
+                    .ok_or(format!("invalid retry count `{}`: expected a positive number", value))?;
+                parsed.max_retries = Some(max_retries);
+            }
+            flag if flag.starts_with("--") => return Err(format!("unknown flag `{}`", flag)),
+            _ if index.is_none() => index = Some(arg),
+            value => return Err(format!("unexpected argument `{}`", value)),
+        }
+    }
+    if parsed.keygen {
+        // Only the parameters of the keys and the output format apply.
+        if let Some(flag) = flags.iter().find(|flag| !matches!(flag.as_str(), "--params" | "--multibit" | "--format")) {
+            return Err(format!("keygen does not support {}", flag));
+        }
+        if index.is_some() {
+            return Err("keygen takes no index".to_string());
+        }
+        return Ok(parsed);
+    }
+    if parsed.estimate {
+        // Only what selects the strategies and the output format applies.
+        const ESTIMATE_FLAGS: [&str; 6] =
+            ["--estimate", "--strategy", "--skip-additions", "--skip-lookup", "--sequence", "--format"];
+        if let Some(flag) = flags.iter().find(|flag| !ESTIMATE_FLAGS.contains(&flag.as_str())) {
+            return Err(format!("--estimate does not support {}", flag));
+        }
+        if index.is_some() {
+            return Err("--estimate takes no index: the work does not depend on it".to_string());
+        }
+        if parsed.sequence == SequenceChoice::Negafibonacci {
+            return Err("--estimate does not support --sequence negafib".to_string());
+        }
+    }
+    // Negafibonacci indices are signed: they are parsed once every shared check
+    // below has run.
+    if parsed.sequence != SequenceChoice::Negafibonacci {
+        parsed.n = index.as_deref().map(parse_index).transpose()?;
+    }
+    if parsed.max_retries.is_some() && (index.is_some() || parsed.batch.is_some() || parsed.ciphertext_stdin) {
+        return Err("--max-retries only applies to the interactive prompt: drop the index, --batch and --ciphertext-stdin".to_string());
+    }
+    if parsed.log_csv.is_some() && (parsed.batch.is_some() || parsed.ciphertext_stdin) {
+        return Err("--log-csv logs single queries, not --batch or --ciphertext-stdin".to_string());
+    }
+    // JSON results are numbers; only text output has a radix.
+    if parsed.format == OutputFormat::Json && flags.iter().any(|flag| flag == "--radix") {
+        return Err("--radix only applies to --format text".to_string());
+    }
+    if parsed.batch.is_some() && parsed.format == OutputFormat::Json {
+        return Err("--format json is not supported with --batch".to_string());
+    }
+    if parsed.threads.is_some() && !cfg!(feature = "parallel") {
+        return Err("--threads needs the `parallel` feature".to_string());
+    }
+    if parsed.plaintext {
+        if index.is_none() {
+            return Err("--plaintext needs an index".to_string());
+        }
+        // No key is generated and no table built: these flags would do nothing.
+        const FHE_ONLY: [&str; 10] = [
+            "--keys",
+            "--compressed-keys",
+            "--tables",
+            "--params",
+            "--multibit",
+            "--seed",
+            "--trivial",
+            "--threads",
+            "--batch",
+            "--ciphertext-stdin",
+        ];
+        if let Some(flag) = FHE_ONLY.iter().find(|fhe_only| flags.iter().any(|flag| flag == *fhe_only)) {
+            return Err(format!("--plaintext does not support {}", flag));
+        }
+    }
+    if parsed.ciphertext_stdin {
+        if parsed.keys.is_none() {
+            return Err("--ciphertext-stdin needs the server keys of --keys".to_string());
+        }
+        if parsed.sequence != SequenceChoice::Fibonacci {
+            return Err("--ciphertext-stdin only supports the Fibonacci sequence".to_string());
+        }
+        if index.is_some() || parsed.batch.is_some() || parsed.seed.is_some() || parsed.trivial {
+            return Err("--ciphertext-stdin reads its input from stdin: drop the index, --batch, --seed and --trivial".to_string());
+        }
+        // The server loads the keys as stored, builds fresh tables on the global
+        // rayon pool, always runs the lookup and writes raw bytes: these flags
+        // would silently do nothing.
+        const IGNORED: [&str; 11] = [
+            "--tables",
+            "--threads",
+            "--params",
+            "--multibit",
+            "--compressed-keys",
+            "--format",
+            "--strategy",
+            "--skip-additions",
+            "--skip-lookup",
+            "--radix",
+            "--u8",
+        ];
+        if let Some(flag) = IGNORED.iter().find(|ignored| flags.iter().any(|flag| flag == *ignored)) {
+            return Err(format!("--ciphertext-stdin does not support {}", flag));
+        }
+    }
+    if parsed.seed.is_some() && (parsed.keys.is_some() || parsed.compressed_keys) {
+        return Err("--seed generates fresh keys, drop --keys and --compressed-keys".to_string());
+    }
+    if parsed.skip_additions && parsed.skip_lookup {
+        return Err("--skip-additions and --skip-lookup cannot be combined".to_string());
+    }
+    if parsed.skip_lookup && (parsed.batch.is_some() || parsed.sequence != SequenceChoice::Fibonacci) {
+        return Err("--skip-lookup leaves nothing to run: --batch and other sequences only use the lookup".to_string());
+    }
+    if !parsed.runs_additions() && !parsed.runs_lookup() && !parsed.runs_matrix() {
+        return Err("every strategy selected by --strategy is skipped".to_string());
+    }
+    if parsed.batch.is_some() && parsed.trivial {
+        return Err("--trivial is not supported with --batch".to_string());
+    }
+    // Tables are encrypted under the public key: with fresh random keys, a cache
+    // from an earlier run would decrypt to garbage.
+    if parsed.tables.is_some() && parsed.keys.is_none() && parsed.seed.is_none() {
+        return Err("--tables needs the keys the tables were built with, add --keys or --seed".to_string());
+    }
+    if parsed.tables.is_some() && parsed.trivial {
+        return Err("--trivial tables are never cached, drop --tables".to_string());
+    }
+    if parsed.u8_output {
+        if parsed.sequence.max_index_u8().is_none() {
+            return Err(format!("--u8 is not supported for {}", parsed.sequence.name()));
+        }
+        if parsed.tables.is_some() || parsed.trivial {
+            return Err("--u8 tables are neither cached nor trivial, drop --tables and --trivial".to_string());
+        }
+        // The other sequences only run the lookup; for fib, the additions and
+        // matrix strategies compute over FheUint16 and the batch lookup reads
+        // the 16-bit table.
+        let fib_runs_more = parsed.runs_additions() || parsed.runs_matrix() || parsed.batch.is_some();
+        if parsed.sequence == SequenceChoice::Fibonacci && fib_runs_more {
+            return Err("--u8 only runs the lookup: add --strategy lookup and drop --batch".to_string());
+        }
+    }
+    if parsed.sequence != SequenceChoice::Fibonacci {
+        // Only the lookup exists for the other sequences: any other strategy
+        // would be silently replaced by it.
+        if flags.iter().any(|flag| flag == "--strategy") && parsed.strategy != StrategyChoice::Lookup {
+            return Err(format!("{} only supports --strategy lookup", parsed.sequence.name()));
+        }
+        if parsed.batch.is_some() {
+            return Err("--batch is only supported for the Fibonacci sequence".to_string());
+        }
+    }
+    let max_index = parsed.max_index();
+    if parsed.n.is_some_and(|n| n > max_index) {
+        return Err(format!(
+            "{} index must be between 0 and {}",
+            parsed.sequence.name(),
+            max_index
+        ));
+    }
+    if parsed.sequence == SequenceChoice::Negafibonacci {
+        if parsed.batch.is_some()
+            || parsed.trivial
+            || parsed.tables.is_some()
+            || parsed.plaintext
+            || parsed.log_csv.is_some()
+            || parsed.max_retries.is_some()
+            || flags.iter().any(|flag| flag == "--radix")
+        {
+            return Err(
+                "--sequence negafib does not support --batch, --trivial, --tables, --plaintext, --log-csv, --radix or --max-retries"
+                    .to_string(),
+            );
+        }
+        let index = index.ok_or("--sequence negafib needs an index")?;
+        parsed.signed_n = Some(parse_signed_index(&index)?);
+    }
+    Ok(parsed)
+}
+
+/// Parse an index in `0..=MAX_FIBONACCI_INDEX`.
+pub fn parse_index(value: &str) -> Result<u16, String> {
+    value
+        .parse::<u16>()
+        .ok()
+        .filter(|n| *n <= MAX_FIBONACCI_INDEX)
+        .ok_or(format!(
+            "invalid index `{}`: expected a number between 0 and {}",
+            value, MAX_FIBONACCI_INDEX
+        ))
+}
+
+/// Parse a signed index in `-MAX_NEGAFIBONACCI_INDEX..=MAX_NEGAFIBONACCI_INDEX`.
+pub fn parse_signed_index(value: &str) -> Result<i16, String> {
+    value
+        .parse::<i16>()
+        .ok()
+        .filter(|n| n.unsigned_abs() <= MAX_NEGAFIBONACCI_INDEX.unsigned_abs())
+        .ok_or(format!(
+            "invalid index `{}`: expected a number between -{} and {}",
+            value, MAX_NEGAFIBONACCI_INDEX, MAX_NEGAFIBONACCI_INDEX
+        ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(parse(&[]).unwrap(), Args::default());
+        // run::run reproduces the default flow without going through Args.
+        assert_eq!(Args::default().config(), FibConfig::default());
+        assert_eq!(Args::default().selection(), Selection::from(StrategyChoice::default()));
+
+        let args = parse(&["10", "--strategy", "lookup"]).unwrap();
+        assert_eq!(args.n, Some(10));
+        assert_eq!(args.strategy, StrategyChoice::Lookup);
+
+        assert!(parse(&["25"]).is_err());
+        assert!(parse(&["-1"]).is_err());
+        assert!(parse(&["--strategy", "fast"]).is_err());
+        assert!(parse(&["1", "2"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_batch() {
+        assert_eq!(parse(&["--batch", "3,5,8,13"]).unwrap().batch, Some(vec![3, 5, 8, 13]));
+        assert!(parse(&["--batch", "3,25"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_sequence() {
+        assert_eq!(parse(&["--sequence", "lucas", "23"]).unwrap().sequence, SequenceChoice::Lucas);
+        assert!(parse(&["--sequence", "lucas", "24"]).is_err());
+        assert_eq!(parse(&["--sequence", "pell", "13"]).unwrap().sequence, SequenceChoice::Pell);
+        assert!(parse(&["--sequence", "pell", "14"]).is_err());
+        assert_eq!(parse(&["--sequence", "tribonacci", "20"]).unwrap().sequence, SequenceChoice::Tribonacci);
+        assert!(parse(&["--sequence", "tribonacci", "21"]).is_err());
+        assert!(parse(&["--sequence", "lucas", "--strategy", "lookup", "5"]).is_ok());
+        assert!(parse(&["--sequence", "lucas", "--strategy", "additions", "5"]).is_err());
+        assert!(parse(&["--sequence", "pell", "--strategy", "all", "5"]).is_err());
+
+        let args = parse(&["-7", "--sequence", "negafib"]).unwrap();
+        assert_eq!((args.n, args.signed_n), (None, Some(-7)));
+        assert!(parse(&["--sequence", "negafib", "-13"]).is_err());
+        assert!(parse(&["--sequence", "negafib"]).is_err());
+        assert!(parse(&["-7"]).is_err());
+        // Negafib goes through the same checks as the other lookup-only sequences.
+        for flags in [
+            &["--u8"][..],
+            &["--skip-lookup"],
+            &["--strategy", "additions"],
+            &["--seed", "7", "--keys", "keys/"],
+        ] {
+            let args: Vec<_> = ["--sequence", "negafib", "-3"].iter().chain(flags).copied().collect();
+            assert!(parse(&args).is_err(), "{:?} is accepted with --sequence negafib", flags);
+        }
+        assert_eq!(
+            parse(&["--sequence", "negafib", "--threads", "2", "-3"]).is_ok(),
+            cfg!(feature = "parallel")
+        );
+    }
+
+    #[test]
+    fn test_parse_format_and_radix() {
+        assert_eq!(parse(&["--format", "json"]).unwrap().format, OutputFormat::Json);
+        assert!(parse(&["--format", "yaml"]).is_err());
+
+        assert_eq!(parse(&["--radix", "hex", "10"]).unwrap().radix, Radix::Hex);
+        assert!(parse(&["--radix", "oct"]).is_err());
+        assert!(parse(&["--radix", "bin", "--format", "json"]).is_err());
+        assert!(parse(&["--radix", "dec", "--sequence", "negafib", "-3"]).is_err());
+        assert!(parse(&["--radix", "bin", "--ciphertext-stdin", "--keys", "keys/"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_params() {
+        assert_eq!(parse(&["--params", "fast"]).unwrap().params, ParamSet::Fast);
+        assert!(parse(&["--params", "slow"]).is_err());
+
+        let args = parse(&["--params", "fast", "--multibit", "10"]).unwrap();
+        assert_eq!(args.config(), FibConfig { params: ParamSet::Fast, multibit: true });
+        assert!(!parse(&["10"]).unwrap().config().multibit);
+        assert!(parse(&["keygen", "--multibit"]).unwrap().multibit);
+        assert!(parse(&["--plaintext", "--multibit", "12"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_threads() {
+        if cfg!(feature = "parallel") {
+            assert_eq!(parse(&["--threads", "1"]).unwrap().threads, Some(1));
+        } else {
+            assert!(parse(&["--threads", "1"]).is_err());
+        }
+        assert!(parse(&["--threads", "0"]).is_err());
+        assert!(parse(&["--threads", "many"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_max_retries() {
+        assert_eq!(parse(&[]).unwrap().retry_limit(), DEFAULT_MAX_RETRIES);
+        assert_eq!(parse(&["--max-retries", "5"]).unwrap().retry_limit(), 5);
+        assert!(parse(&["--max-retries", "0"]).is_err());
+        assert!(parse(&["--max-retries", "5", "10"]).is_err());
+        assert!(parse(&["--max-retries", "5", "--batch", "3,5"]).is_err());
+        assert!(parse(&["--sequence", "negafib", "--max-retries", "5", "-3"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_trivial_and_tables() {
+        assert!(parse(&["--trivial", "10"]).unwrap().trivial);
+        assert!(parse(&["--trivial", "--batch", "3,5"]).is_err());
+
+        let args = parse(&["--keys", "keys/", "--tables", "tables/"]).unwrap();
+        assert_eq!(args.tables, Some(PathBuf::from("tables/")));
+        assert!(parse(&["--seed", "7", "--tables", "tables/"]).is_ok());
+        assert!(parse(&["--tables", "tables/"]).is_err());
+        assert!(parse(&["--tables"]).is_err());
+        assert!(parse(&["--keys", "keys/", "--tables", "tables/", "--trivial"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_ciphertext_stdin() {
+        assert!(parse(&["--ciphertext-stdin", "--keys", "keys/"]).unwrap().ciphertext_stdin);
+        assert!(parse(&["--ciphertext-stdin"]).is_err());
+        assert!(parse(&["--ciphertext-stdin", "--keys", "keys/", "9"]).is_err());
+        assert!(parse(&["--ciphertext-stdin", "--keys", "keys/", "--sequence", "negafib", "-3"]).is_err());
+        assert!(parse(&["--ciphertext-stdin", "--keys", "keys/", "--sequence", "negafib"]).is_err());
+        for flag in [&["--tables", "tables/"][..], &["--threads", "2"], &["--params", "default"], &["--compressed-keys"], &["--format", "text"], &["--u8"]] {
+            let args: Vec<_> = ["--ciphertext-stdin", "--keys", "keys/"].iter().chain(flag).copied().collect();
+            assert!(parse(&args).is_err(), "{:?} is ignored by --ciphertext-stdin", flag);
+        }
+    }
+
+    #[test]
+    fn test_parse_keygen() {
+        let args = parse(&["keygen", "--params", "fast", "--format", "json"]).unwrap();
+        assert!(args.keygen);
+        assert_eq!(args.params, ParamSet::Fast);
+        assert!(parse(&["keygen", "--keys", "keys/"]).is_err());
+        assert!(parse(&["keygen", "10"]).is_err());
+        assert!(parse(&["10", "keygen"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_log_csv() {
+        assert_eq!(parse(&["--log-csv", "runs.csv", "10"]).unwrap().log_csv, Some(PathBuf::from("runs.csv")));
+        assert!(parse(&["--log-csv"]).is_err());
+        assert!(parse(&["--log-csv", "runs.csv", "--batch", "3,5"]).is_err());
+        assert!(parse(&["--log-csv", "runs.csv", "--sequence", "negafib", "-3"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_estimate() {
+        assert!(parse(&["--estimate"]).unwrap().estimate);
+        assert!(parse(&["--estimate", "--strategy", "lookup", "--format", "json"]).is_ok());
+        assert!(parse(&["--estimate", "--sequence", "pell"]).is_ok());
+        assert!(parse(&["--estimate", "12"]).is_err());
+        assert!(parse(&["--estimate", "--keys", "keys/"]).is_err());
+        assert!(parse(&["--estimate", "--sequence", "negafib"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_plaintext() {
+        assert!(parse(&["--plaintext", "12"]).unwrap().plaintext);
+        assert!(parse(&["--plaintext", "--sequence", "lucas", "--format", "json", "12"]).is_ok());
+        assert!(parse(&["--plaintext"]).is_err());
+        assert!(parse(&["--plaintext", "--keys", "keys/", "12"]).is_err());
+        assert!(parse(&["--plaintext", "--batch", "3,5"]).is_err());
+        assert!(parse(&["--plaintext", "--sequence", "negafib", "-3"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_seed() {
+        assert_eq!(parse(&["--seed", "42", "10"]).unwrap().seed, Some(42));
+        assert!(parse(&["--seed", "-1"]).is_err());
+        assert!(parse(&["--seed", "42", "--keys", "keys/"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_strategy_skip() {
+        let args = parse(&["--skip-additions", "10"]).unwrap();
+        assert!(!args.runs_additions() && args.runs_lookup() && args.runs_matrix());
+        let args = parse(&["--strategy", "both", "--skip-lookup", "10"]).unwrap();
+        assert!(args.runs_additions() && !args.runs_lookup() && !args.runs_matrix());
+        assert!(parse(&["--skip-additions", "--skip-lookup", "10"]).is_err());
+        assert!(parse(&["--strategy", "lookup", "--skip-lookup", "10"]).is_err());
+        assert!(parse(&["--sequence", "lucas", "--skip-lookup", "10"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_u8() {
+        let args = parse(&["--sequence", "tribonacci", "--u8", "11"]).unwrap();
+        assert!(args.u8_output);
+        assert_eq!(args.max_index(), 11);
+        assert!(parse(&["--sequence", "tribonacci", "--u8", "12"]).is_err());
+        assert!(parse(&["--sequence", "pell", "--u8", "--trivial"]).is_err());
+        assert!(parse(&["--sequence", "negafib", "--u8", "-3"]).is_err());
+
+        let args = parse(&["--strategy", "lookup", "--u8", "13"]).unwrap();
+        assert_eq!(args.max_index(), 13);
+        assert!(parse(&["--strategy", "lookup", "--u8", "14"]).is_err());
+        assert!(parse(&["--u8", "10"]).is_err());
+        assert!(parse(&["--strategy", "all", "--skip-additions", "--u8", "10"]).is_err());
+        assert!(parse(&["--strategy", "lookup", "--u8", "--batch", "3,5"]).is_err());
+    }
+
+    #[test]
+    fn test_radix_format() {
+        assert_eq!(Radix::Dec.format(55), "55");
+        assert_eq!(Radix::Hex.format(55), "0x37");
+        assert_eq!(Radix::Bin.format(55), "0b110111");
+    }
+}
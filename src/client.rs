@@ -0,0 +1,61 @@
+//! Client-side operations, exchanged with the server as serialized bytes.
+//!
+//! This module only encrypts and decrypts: it needs neither the server key nor
+//! rayon, stdin or the file system, so it is what a browser client built with
+//! `--no-default-features --features wasm` uses while the lookup runs on the
+//! server.
+
+use crate::serialization::{read_ciphertext, write_ciphertext};
+use crate::{FheUint16, PublicKey};
+use std::io;
+use tfhe::prelude::*;
+use tfhe::ClientKey;
+
+/// Encrypt the index `n` with the public key and serialize it for the server.
+pub fn encrypt_index(n: u16, public_key: &PublicKey) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    write_ciphertext(&FheUint16::encrypt(n, public_key), &mut bytes).expect("tfhe values are serializable");
+    bytes
+}
+
+/// Deserialize a result returned by the server and decrypt it.
+pub fn decrypt_result(bytes: &[u8], client_key: &ClientKey) -> io::Result<u16> {
+    Ok(decrypt_value(&read_ciphertext(bytes)?, client_key))
+}
+
+/// Decrypt `ct` into the clear type `T` picked by the caller, e.g.
+/// `decrypt_value::<u32>(&ct, &client_key)`, instead of annotating the binding.
+///
+/// Targets narrower than 16 bits keep the low bits of the value.
+pub fn decrypt_value<T>(ct: &FheUint16, client_key: &ClientKey) -> T
+where
+    FheUint16: FheDecrypt<T>,
+{
+    ct.decrypt(client_key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestHarness;
+
+    #[test]
+    fn test_encrypt_decrypt_bytes() {
+        let harness = TestHarness::new();
+
+        let bytes = encrypt_index(13, harness.pks());
+        assert_eq!(decrypt_result(&bytes, harness.client_key()).unwrap(), 13);
+        assert!(decrypt_result(&bytes[..bytes.len() / 2], harness.client_key()).is_err());
+    }
+
+    #[test]
+    fn test_decrypt_value() {
+        let harness = TestHarness::new();
+        let client_key = harness.client_key();
+        let ct = FheUint16::encrypt(46368u16, client_key);
+
+        assert_eq!(decrypt_value::<u16>(&ct, client_key), 46368);
+        assert_eq!(decrypt_value::<u32>(&ct, client_key), 46368);
+        assert_eq!(decrypt_value::<u8>(&ct, client_key), 46368u16 as u8);
+    }
+}
@@ -0,0 +1,80 @@
+//! Encrypted tables built with a compact public key, with the `compact` feature.
+//!
+//! The index and Fibonacci tables are encrypted with a [`CompactPublicKey`] into
+//! a single packed [`CompactCiphertextList`]. That is the form a server stores:
+//! far fewer bytes than 25 individual ciphertexts under a
+//! [`PublicKey`](crate::PublicKey). The list is expanded back into `FheUint16`s
+//! with [`expand_table`] before the lookup.
+//!
+//! Unlike [`compression`](crate::compression), building the list needs no
+//! server key and works with the default config; expanding a packed list does
+//! need the server key set on the calling thread.
+
+use crate::{build_fibonacci_table_plain, FheUint16, MAX_FIBONACCI_INDEX};
+use tfhe::prelude::*;
+use tfhe::{CompactCiphertextList, CompactPublicKey};
+
+/// Encrypt the index table with a compact public key into one packed list;
+/// entry `i` is an encryption of `i`, as in
+/// [`build_encrypted_indices`](crate::build_encrypted_indices).
+pub fn build_encrypted_indices_compact(pks: &CompactPublicKey) -> CompactCiphertextList {
+    build_compact_table((0..=MAX_FIBONACCI_INDEX).collect(), pks)
+}
+
+/// Encrypt the Fibonacci table with a compact public key into one packed list,
+/// aligned with [`build_encrypted_indices_compact`].
+pub fn build_encrypted_fibs_compact(pks: &CompactPublicKey) -> CompactCiphertextList {
+    build_compact_table(build_fibonacci_table_plain(), pks)
+}
+
+fn build_compact_table(values: Vec<u16>, pks: &CompactPublicKey) -> CompactCiphertextList {
+    CompactCiphertextList::builder(pks)
+        .extend(values.into_iter())
+        .build_packed()
+}
+
+/// Expand a list built by [`build_encrypted_indices_compact`] or
+/// [`build_encrypted_fibs_compact`] into ciphertexts usable by the lookup.
+///
+/// Requires the server key to be set on the calling thread.
+pub fn expand_table(list: &CompactCiphertextList) -> tfhe::Result<Vec<FheUint16>> {
+    let expander = list.expand()?;
+    (0..expander.len())
+        .map(|i| expander.get::<FheUint16>(i).map(|ct| ct.expect("index is below expander.len()")))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serialization::{compact_table_bytes, encrypted_table_bytes};
+    use crate::fibonacci_lookup_with_tables;
+    use crate::test_support::TestHarness;
+
+    #[test]
+    fn test_compact_tables() {
+        let harness = TestHarness::new();
+        let compact_pks = CompactPublicKey::new(harness.client_key());
+
+        harness.run(|| {
+            let compact_indices = build_encrypted_indices_compact(&compact_pks);
+            let compact_fibs = build_encrypted_fibs_compact(&compact_pks);
+            let expanded_indices = expand_table(&compact_indices).unwrap();
+            let expanded_fibs = expand_table(&compact_fibs).unwrap();
+
+            assert_eq!(expanded_indices.len(), usize::from(MAX_FIBONACCI_INDEX) + 1);
+            assert!(compact_table_bytes(&compact_indices) < encrypted_table_bytes(harness.indices()));
+            assert!(compact_table_bytes(&compact_fibs) < encrypted_table_bytes(harness.fibs()));
+
+            for n in [0u16, 7, MAX_FIBONACCI_INDEX] {
+                let encrypted = harness.encrypt(n);
+                let dec_compact: u16 =
+                    harness.decrypt(&fibonacci_lookup_with_tables(&encrypted, &expanded_indices, &expanded_fibs));
+                let dec: u16 =
+                    harness.decrypt(&fibonacci_lookup_with_tables(&encrypted, harness.indices(), harness.fibs()));
+                assert_eq!(dec_compact, dec, "compact lookup mismatch for n = {}", n);
+                assert_eq!(dec_compact, crate::fibonacci_plaintext(n));
+            }
+        });
+    }
+}
@@ -0,0 +1,102 @@
+//! Compressed encrypted tables.
+//!
+//! The index and Fibonacci tables are encrypted with a [`CompressedPublicKey`]
+//! and packed into a [`CompressedCiphertextList`], which is much smaller to store
+//! or send than 25 individual ciphertexts. The list is expanded back into
+//! `FheUint16`s before the lookup.
+//!
+//! Packing uses the compression key carried by the server key, so the keys must
+//! be generated from [`compression_config`] and the server key must be set on
+//! the calling thread.
+
+use crate::{build_fibonacci_table_plain, FheUint16, MAX_FIBONACCI_INDEX};
+use crate::par::*;
+use tfhe::prelude::*;
+use tfhe::shortint::parameters::COMP_PARAM_MESSAGE_2_CARRY_2;
+use tfhe::{
+    CompressedCiphertextList, CompressedCiphertextListBuilder, CompressedPublicKey, Config,
+    ConfigBuilder,
+};
+
+/// Default config with ciphertext compression enabled.
+pub fn compression_config() -> Config {
+    ConfigBuilder::default()
+        .enable_compression(COMP_PARAM_MESSAGE_2_CARRY_2)
+        .build()
+}
+
+/// Build the encrypted index table with a compressed public key and pack it.
+///
+/// Packing requires the server key to be set, as for [`compress_table`].
+pub fn build_compressed_indices(pks: &CompressedPublicKey) -> tfhe::Result<CompressedCiphertextList> {
+    let indices: Vec<FheUint16> = (0..=MAX_FIBONACCI_INDEX)
+        .into_par_iter()
+        .map(|i| FheUint16::encrypt(i, pks))
+        .collect();
+    compress_table(indices)
+}
+
+/// Build the encrypted Fibonacci table with a compressed public key and pack it.
+///
+/// Packing requires the server key to be set, as for [`compress_table`].
+pub fn build_compressed_fibs(pks: &CompressedPublicKey) -> tfhe::Result<CompressedCiphertextList> {
+    let fibs: Vec<FheUint16> = build_fibonacci_table_plain()
+        .par_iter()
+        .copied()
+        .map(|v| FheUint16::encrypt(v, pks))
+        .collect();
+    compress_table(fibs)
+}
+
+/// Pack a table into a single compressed list.
+///
+/// Requires a server key generated from [`compression_config`] to be set on the
+/// calling thread.
+pub fn compress_table(table: Vec<FheUint16>) -> tfhe::Result<CompressedCiphertextList> {
+    CompressedCiphertextListBuilder::new()
+        .extend(table.into_iter())
+        .build()
+}
+
+/// Expand a list built by [`compress_table`] back into ciphertexts usable by the
+/// lookup.
+///
+/// Requires the server key to be set on the calling thread.
+pub fn decompress_table(list: &CompressedCiphertextList) -> tfhe::Result<Vec<FheUint16>> {
+    (0..list.len())
+        .map(|i| list.get::<FheUint16>(i).map(|ct| ct.expect("index is below list.len()")))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fibonacci_lookup_with_tables;
+    use crate::serialization::{compressed_table_bytes, encrypted_table_bytes};
+    use crate::test_support::Fixture;
+
+    #[test]
+    fn test_compressed_tables() {
+        let fixture = Fixture::new(compression_config());
+        let harness = fixture.harness();
+        let pks = CompressedPublicKey::new(harness.client_key());
+
+        harness.run(|| {
+            let compressed_indices = build_compressed_indices(&pks).unwrap();
+            let compressed_fibs = build_compressed_fibs(&pks).unwrap();
+            let encrypted_indices = decompress_table(&compressed_indices).unwrap();
+            let encrypted_fibs = decompress_table(&compressed_fibs).unwrap();
+
+            assert_eq!(encrypted_indices.len(), usize::from(MAX_FIBONACCI_INDEX) + 1);
+            assert!(compressed_table_bytes(&compressed_indices) < encrypted_table_bytes(&encrypted_indices));
+            assert!(compressed_table_bytes(&compressed_fibs) < encrypted_table_bytes(&encrypted_fibs));
+
+            for n in [0u16, 7, MAX_FIBONACCI_INDEX] {
+                let encrypted = harness.encrypt(n);
+                let enc_lt = fibonacci_lookup_with_tables(&encrypted, &encrypted_indices, &encrypted_fibs);
+                let dec_lt: u16 = harness.decrypt(&enc_lt);
+                assert_eq!(dec_lt, crate::fibonacci_plaintext(n));
+            }
+        });
+    }
+}
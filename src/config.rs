@@ -0,0 +1,97 @@
+//! Parameter choices used to generate the keys.
+//!
+//! [`FibConfig`] is a small wrapper over tfhe's `ConfigBuilder` exposing the knobs
+//! worth experimenting with here:
+//!
+//! - [`ParamSet`]: the block parameters. `Default` keeps tfhe's defaults (2 bits
+//!   of message and carry per block, `2^-128` failure probability); `Fast` uses the
+//!   smaller `2^-64` variant, which speeds up every homomorphic op at the cost of a
+//!   higher bootstrapping failure probability.
+//! - `multibit`: the multi-bit variant of the parameter set, whose programmable
+//!   bootstrap (PBS) goes through the key in groups of 4 elements and spreads
+//!   that work over several threads. tfhe derives the number of PBS threads from that
+//!   grouping factor: 9 threads per bootstrap with a factor of 4. Latency per
+//!   operation drops on a machine with many idle cores; with few cores, the
+//!   extra work of each bootstrap makes it slower. The keys are larger too.
+
+use tfhe::shortint::parameters::v1_3::multi_bit::tuniform::{p_fail_2_minus_128, p_fail_2_minus_64};
+use tfhe::shortint::parameters::PARAM_MESSAGE_2_CARRY_2_KS_PBS_TUNIFORM_2M64;
+use tfhe::{Config, ConfigBuilder};
+
+/// Block parameter set.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ParamSet {
+    /// tfhe's default parameters.
+    #[default]
+    Default,
+    /// `PARAM_MESSAGE_2_CARRY_2_KS_PBS_TUNIFORM_2M64`.
+    Fast,
+}
+
+/// Configuration used to generate the keys; the default matches
+/// `ConfigBuilder::default()`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FibConfig {
+    pub params: ParamSet,
+    /// Use the multi-bit variant of `params`, with a grouping factor of 4.
+    pub multibit: bool,
+}
+
+impl FibConfig {
+    pub fn with_params(params: ParamSet) -> Self {
+        Self { params, multibit: false }
+    }
+
+    /// Build the tfhe config.
+    pub fn build(self) -> Config {
+        match (self.params, self.multibit) {
+            (ParamSet::Default, false) => ConfigBuilder::default(),
+            (ParamSet::Fast, false) => {
+                ConfigBuilder::with_custom_parameters(PARAM_MESSAGE_2_CARRY_2_KS_PBS_TUNIFORM_2M64)
+            }
+            (ParamSet::Default, true) => ConfigBuilder::with_custom_parameters(
+                p_fail_2_minus_128::ks_pbs::V1_3_PARAM_MULTI_BIT_GROUP_4_MESSAGE_2_CARRY_2_KS_PBS_TUNIFORM_2M128,
+            ),
+            (ParamSet::Fast, true) => ConfigBuilder::with_custom_parameters(
+                p_fail_2_minus_64::ks_pbs::V1_3_PARAM_MULTI_BIT_GROUP_4_MESSAGE_2_CARRY_2_KS_PBS_TUNIFORM_2M64,
+            ),
+        }
+        .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::Fixture;
+    use crate::{fibonacci_lookup_with_tables, fibonacci_plaintext};
+
+    #[test]
+    fn test_param_sets() {
+        for params in [ParamSet::Default, ParamSet::Fast] {
+            let fixture = Fixture::new(FibConfig::with_params(params).build());
+            let harness = fixture.harness();
+
+            let encrypted = harness.encrypt(10);
+            let enc_lt = harness.run(|| fibonacci_lookup_with_tables(&encrypted, harness.indices(), harness.fibs()));
+            let dec_lt: u16 = harness.decrypt(&enc_lt);
+
+            assert_eq!(dec_lt, 55, "Lookup mismatch with {:?} parameters", params);
+        }
+    }
+
+    #[test]
+    fn test_multibit_params() {
+        let fixture = Fixture::new(FibConfig { multibit: true, ..FibConfig::default() }.build());
+        let harness = fixture.harness();
+        harness.run(|| {
+            for n in 0..=10u16 {
+                let encrypted = harness.encrypt(n);
+                let enc_lt = fibonacci_lookup_with_tables(&encrypted, harness.indices(), harness.fibs());
+                let dec_lt: u16 = harness.decrypt(&enc_lt);
+
+                assert_eq!(dec_lt, fibonacci_plaintext(n), "Lookup mismatch for n = {} with multi-bit parameters", n);
+            }
+        });
+    }
+}
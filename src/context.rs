@@ -0,0 +1,80 @@
+//! Scoped server keys.
+//!
+//! tfhe keeps the server key in thread-local state set by
+//! [`tfhe::set_server_key`], which a library user may already have set to a
+//! different key. [`with_server_key`] confines a key to one closure and puts
+//! back the key of the enclosing [`with_server_key`] call, if any, afterwards.
+
+use std::cell::RefCell;
+use tfhe::{set_server_key, unset_server_key, ServerKey};
+
+thread_local! {
+    /// Keys of the `with_server_key` calls running on this thread, innermost last.
+    static SCOPED_KEYS: RefCell<Vec<ServerKey>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Run `f` with `server_key` set on the calling thread.
+///
+/// Calls nest: when `f` returns (or panics) the key of the enclosing call is set
+/// again, or no key at all when this is the outermost call. A key set directly
+/// with [`tfhe::set_server_key`] outside of any call is not restored.
+///
+/// Only the calling thread sees the key; functions that fan out to rayon workers
+/// take the server key explicitly, e.g.
+/// [`fibonacci_lookup_batch`](crate::fibonacci_lookup_batch), and scope it with
+/// `with_server_key` around each unit of work, so no key outlives the call on
+/// the pool. The calling thread may run some of that work itself, which unsets a
+/// key it had set directly with [`tfhe::set_server_key`]: call such functions
+/// inside `with_server_key` instead.
+pub fn with_server_key<T>(server_key: ServerKey, f: impl FnOnce() -> T) -> T {
+    SCOPED_KEYS.with_borrow_mut(|keys| keys.push(server_key.clone()));
+    set_server_key(server_key);
+    let _restore = RestoreOnDrop;
+    f()
+}
+
+/// Pops the innermost scoped key and sets the enclosing one back.
+struct RestoreOnDrop;
+
+impl Drop for RestoreOnDrop {
+    fn drop(&mut self) {
+        let enclosing = SCOPED_KEYS.with_borrow_mut(|keys| {
+            keys.pop();
+            keys.last().cloned()
+        });
+        match enclosing {
+            Some(server_key) => set_server_key(server_key),
+            None => unset_server_key(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tfhe::prelude::*;
+    use tfhe::{generate_keys, ConfigBuilder, FheUint16};
+
+    #[test]
+    fn test_nested_server_keys() {
+        let config = ConfigBuilder::default().build();
+        let (outer_client_key, outer_server_key) = generate_keys(config);
+        let (inner_client_key, inner_server_key) = generate_keys(config);
+
+        let add = |client_key| {
+            let a = FheUint16::encrypt(3u16, client_key);
+            let b = FheUint16::encrypt(5u16, client_key);
+            let sum: u16 = (&a + &b).decrypt(client_key);
+            sum
+        };
+
+        let (inner_sum, outer_sum) = with_server_key(outer_server_key, || {
+            let inner_sum = with_server_key(inner_server_key, || add(&inner_client_key));
+            // The outer key is back once the inner call returns.
+            (inner_sum, add(&outer_client_key))
+        });
+        assert_eq!(inner_sum, 8);
+        assert_eq!(outer_sum, 8);
+        assert!(SCOPED_KEYS.with_borrow(Vec::is_empty));
+    }
+}
@@ -0,0 +1,90 @@
+//! Appending one row per computation to a CSV file, for `--log-csv`.
+//!
+//! The file is opened in append mode on every row, so several runs, or a long
+//! interactive session, accumulate into one file that a spreadsheet or a
+//! regression script can read. Results and timings of strategies that did not
+//! run are left empty.
+
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// First line of a new log file.
+pub const CSV_HEADER: &str = "timestamp,input,expected,additions_result,additions_ms,lookup_result,lookup_ms";
+
+/// One computation, as logged by [`append_row`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CsvRow {
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+    pub input: u16,
+    pub expected: u16,
+    pub additions_result: Option<u16>,
+    pub additions_ms: Option<u128>,
+    pub lookup_result: Option<u16>,
+    pub lookup_ms: Option<u128>,
+}
+
+impl CsvRow {
+    /// A row timestamped with the current time.
+    pub fn now(input: u16, expected: u16) -> Self {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs());
+        Self { timestamp, input, expected, ..Self::default() }
+    }
+}
+
+/// Append `row` to the CSV file at `path`, creating it with [`CSV_HEADER`] when
+/// it does not exist or is empty.
+pub fn append_row(path: impl AsRef<Path>, row: &CsvRow) -> io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    if file.metadata()?.len() == 0 {
+        writeln!(file, "{}", CSV_HEADER)?;
+    }
+    let field = |value: Option<String>| value.unwrap_or_default();
+    writeln!(
+        file,
+        "{},{},{},{},{},{},{}",
+        row.timestamp,
+        row.input,
+        row.expected,
+        field(row.additions_result.map(|result| result.to_string())),
+        field(row.additions_ms.map(|ms| ms.to_string())),
+        field(row.lookup_result.map(|result| result.to_string())),
+        field(row.lookup_ms.map(|ms| ms.to_string())),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_append_row() {
+        let dir = std::env::temp_dir().join(format!("fib-fhe-csv-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("log.csv");
+        let _ = fs::remove_file(&path);
+
+        let first = CsvRow {
+            additions_result: Some(55),
+            additions_ms: Some(900),
+            lookup_result: Some(55),
+            lookup_ms: Some(300),
+            ..CsvRow::now(10, 55)
+        };
+        let second = CsvRow { lookup_result: Some(144), lookup_ms: Some(310), ..CsvRow::now(12, 144) };
+        append_row(&path, &first).unwrap();
+        append_row(&path, &second).unwrap();
+
+        let contents = fs::read_to_string(&path).unwrap();
+        let lines: Vec<_> = contents.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], CSV_HEADER);
+        assert_eq!(lines[1], format!("{},10,55,55,900,55,300", first.timestamp));
+        assert_eq!(lines[2], format!("{},12,144,,,144,310", second.timestamp));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,59 @@
+//! Homomorphic operation counts of the strategies, estimated up front.
+//!
+//! The server's work does not depend on the encrypted `n`, so the number of
+//! operations of each strategy follows from the largest index alone. These are
+//! the counts that [`stats`](crate::stats) records with the `stats` feature,
+//! e.g. 24 `eq` and 24 `select` for [`fibonacci_lookup_with_tables`](crate::fibonacci_lookup_with_tables),
+//! computed without keys or ciphertexts.
+
+use crate::{OpCounts, StrategyChoice};
+
+/// Operations `strategy` performs for indices up to `max_index`; `Both` and
+/// `All` add up the strategies they run.
+///
+/// - additions: one `eq` and `select` per index past 0, one `add` per index
+///   past 1;
+/// - lookup: one `eq` and `select` per table entry past `F(0)`;
+/// - matrix: for each of the `b` bits of `max_index`, an `eq` extracting the
+///   bit, a 2x2 product (8 `mul`, 4 `add`) and 4 `select`s, plus a squaring of
+///   the same cost for all bits but the last, and an `eq` and a `select` zeroing
+///   an index past the table.
+pub fn estimate_ops(strategy: StrategyChoice, max_index: u16) -> OpCounts {
+    let max_index = u64::from(max_index);
+    let additions = OpCounts { eq: max_index, select: max_index, add: max_index.saturating_sub(1), mul: 0 };
+    let lookup = OpCounts { eq: max_index, select: max_index, add: 0, mul: 0 };
+    let bits = u64::from(u64::BITS - max_index.leading_zeros());
+    let products = bits + bits.saturating_sub(1);
+    let matrix = OpCounts { eq: bits + 1, select: 4 * bits + 1, add: 4 * products, mul: 8 * products };
+    match strategy {
+        StrategyChoice::Additions => additions,
+        StrategyChoice::Lookup => lookup,
+        StrategyChoice::Matrix => matrix,
+        StrategyChoice::Both => additions + lookup,
+        StrategyChoice::All => additions + lookup + matrix,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MAX_FIBONACCI_INDEX;
+
+    #[test]
+    fn test_estimate_ops() {
+        let additions = OpCounts { eq: 24, select: 24, add: 23, mul: 0 };
+        let lookup = OpCounts { eq: 24, select: 24, add: 0, mul: 0 };
+        // Five bits of 24: five products and four squarings.
+        let matrix = OpCounts { eq: 6, select: 21, add: 36, mul: 72 };
+
+        assert_eq!(estimate_ops(StrategyChoice::Additions, MAX_FIBONACCI_INDEX), additions);
+        assert_eq!(estimate_ops(StrategyChoice::Lookup, MAX_FIBONACCI_INDEX), lookup);
+        assert_eq!(estimate_ops(StrategyChoice::Matrix, MAX_FIBONACCI_INDEX), matrix);
+        assert_eq!(estimate_ops(StrategyChoice::Both, MAX_FIBONACCI_INDEX), additions + lookup);
+        assert_eq!(estimate_ops(StrategyChoice::All, MAX_FIBONACCI_INDEX), additions + lookup + matrix);
+
+        assert_eq!(estimate_ops(StrategyChoice::Lookup, 13), OpCounts { eq: 13, select: 13, add: 0, mul: 0 });
+        assert_eq!(estimate_ops(StrategyChoice::Both, 0), OpCounts::default());
+        assert_eq!(estimate_ops(StrategyChoice::Matrix, 0), OpCounts { eq: 1, select: 1, add: 0, mul: 0 });
+    }
+}
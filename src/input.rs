@@ -0,0 +1,149 @@
+//! Reading indices typed into the interactive session of the `fib-fhe` binary.
+
+use std::fmt;
+use std::io::{self, BufRead};
+use std::num::ParseIntError;
+
+/// Why a line of input could not be turned into an index.
+#[derive(Debug)]
+pub enum InputError {
+    /// Reading from the input failed.
+    Io(io::Error),
+    /// The line is not a `u16`.
+    Parse(ParseIntError),
+    /// The line is a `u16`, but past the maximum supported index.
+    OutOfRange { value: u16 },
+    /// The input was closed before a line could be read.
+    Eof,
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "failed to read input: {}", e),
+            Self::Parse(e) => write!(f, "not a number: {}", e),
+            Self::OutOfRange { value } => write!(f, "{} is out of range", value),
+            Self::Eof => write!(f, "end of input"),
+        }
+    }
+}
+
+impl std::error::Error for InputError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::Parse(e) => Some(e),
+            Self::OutOfRange { .. } | Self::Eof => None,
+        }
+    }
+}
+
+impl From<io::Error> for InputError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<ParseIntError> for InputError {
+    fn from(e: ParseIntError) -> Self {
+        Self::Parse(e)
+    }
+}
+
+/// Parse one line of input as an index in `0..=max`, or `None` for `quit`.
+pub fn parse_input(line: &str, max: u16) -> Result<Option<u16>, InputError> {
+    let line = line.trim();
+    if line == "quit" {
+        return Ok(None);
+    }
+    let value = line.parse::<u16>()?;
+    if value > max {
+        return Err(InputError::OutOfRange { value });
+    }
+    Ok(Some(value))
+}
+
+/// Read and parse one line from `reader`.
+///
+/// A zero-byte read is [`InputError::Eof`]: a closed stdin would otherwise be
+/// read again and again without ever blocking.
+pub fn read_input(mut reader: impl BufRead, max: u16) -> Result<Option<u16>, InputError> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Err(InputError::Eof);
+    }
+    parse_input(&line, max)
+}
+
+/// Call `read` until it returns an index or `None` for `quit`, passing each
+/// invalid line ([`InputError::Parse`] or [`InputError::OutOfRange`]) to
+/// `on_invalid`.
+///
+/// Gives up on the `max_retries`-th invalid line in a row and returns its error,
+/// so an unattended session fed bad input cannot prompt forever. Read failures
+/// and [`InputError::Eof`] are returned at once.
+pub fn read_with_retries(
+    max_retries: u32,
+    mut read: impl FnMut() -> Result<Option<u16>, InputError>,
+    mut on_invalid: impl FnMut(&InputError),
+) -> Result<Option<u16>, InputError> {
+    let mut invalid = 0;
+    loop {
+        match read() {
+            Err(e @ (InputError::Parse(_) | InputError::OutOfRange { .. })) => {
+                invalid += 1;
+                if invalid >= max_retries {
+                    return Err(e);
+                }
+                on_invalid(&e);
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MAX_FIBONACCI_INDEX;
+
+    #[test]
+    fn test_parse_input() {
+        assert!(matches!(
+            parse_input("25", MAX_FIBONACCI_INDEX),
+            Err(InputError::OutOfRange { value: 25 })
+        ));
+        assert!(matches!(parse_input("-1", MAX_FIBONACCI_INDEX), Err(InputError::Parse(_))));
+        assert!(matches!(parse_input("abc", MAX_FIBONACCI_INDEX), Err(InputError::Parse(_))));
+        assert_eq!(parse_input("12", MAX_FIBONACCI_INDEX).unwrap(), Some(12));
+        assert_eq!(parse_input("24", MAX_FIBONACCI_INDEX).unwrap(), Some(24));
+    }
+
+    #[test]
+    fn test_read_input() {
+        assert_eq!(read_input("12\n".as_bytes(), MAX_FIBONACCI_INDEX).unwrap(), Some(12));
+        assert_eq!(read_input(" quit \n".as_bytes(), MAX_FIBONACCI_INDEX).unwrap(), None);
+        assert!(matches!(read_input("".as_bytes(), MAX_FIBONACCI_INDEX), Err(InputError::Eof)));
+        assert!(matches!(read_input("abc\n".as_bytes(), MAX_FIBONACCI_INDEX), Err(InputError::Parse(_))));
+        assert!(matches!(read_input(&[0xff, b'\n'][..], MAX_FIBONACCI_INDEX), Err(InputError::Io(_))));
+    }
+
+    #[test]
+    fn test_read_with_retries() {
+        // Gives up on the third invalid line, before reaching the valid one.
+        let mut reader = "abc\n25\n-1\n12\n".as_bytes();
+        let mut warnings = 0;
+        let result = read_with_retries(3, || read_input(&mut reader, MAX_FIBONACCI_INDEX), |_| warnings += 1);
+        assert!(matches!(result, Err(InputError::Parse(_))));
+        assert_eq!(warnings, 2);
+        assert_eq!(reader, "12\n".as_bytes());
+
+        let mut reader = "abc\n25\n12\n".as_bytes();
+        let result = read_with_retries(3, || read_input(&mut reader, MAX_FIBONACCI_INDEX), |_| {});
+        assert_eq!(result.unwrap(), Some(12));
+
+        let mut reader = "abc\n".as_bytes();
+        let result = read_with_retries(3, || read_input(&mut reader, MAX_FIBONACCI_INDEX), |_| {});
+        assert!(matches!(result, Err(InputError::Eof)));
+    }
+}
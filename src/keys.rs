@@ -0,0 +1,288 @@
+//! Persisting keys across invocations.
+//!
+//! Key generation dominates the run time, so the client, server and public keys
+//! can be written to a directory once and reloaded afterwards. The server key can
+//! also be stored as a [`CompressedServerKey`], which is much smaller on disk and
+//! is decompressed when loaded.
+
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::Path;
+use tfhe::core_crypto::commons::generators::DeterministicSeeder;
+use tfhe::core_crypto::prelude::DefaultRandomGenerator;
+use tfhe::shortint::engine::ShortintEngine;
+use tfhe::{generate_keys, ClientKey, CompressedServerKey, PublicKey, Seed, ServerKey};
+
+use crate::config::FibConfig;
+use crate::serialization::{read_versioned, write_versioned};
+
+const CLIENT_KEY_FILE: &str = "client_key.bin";
+const SERVER_KEY_FILE: &str = "server_key.bin";
+const PUBLIC_KEY_FILE: &str = "public_key.bin";
+const COMPRESSED_SERVER_KEY_FILE: &str = "server_key_compressed.bin";
+
+/// Upper bound on the serialized size of a single key; the public key is the
+/// largest by far.
+const KEY_SIZE_LIMIT: u64 = 1 << 34;
+
+/// The client, server and public keys of one parameter set.
+#[derive(Clone)]
+pub struct Keys {
+    pub client_key: ClientKey,
+    pub server_key: ServerKey,
+    pub public_key: PublicKey,
+}
+
+impl From<(ClientKey, ServerKey, PublicKey)> for Keys {
+    fn from((client_key, server_key, public_key): (ClientKey, ServerKey, PublicKey)) -> Self {
+        Self { client_key, server_key, public_key }
+    }
+}
+
+/// Write the three keys to `dir`, creating it if needed.
+pub fn save_keys(
+    dir: impl AsRef<Path>,
+    client_key: &ClientKey,
+    server_key: &ServerKey,
+    public_key: &PublicKey,
+) -> io::Result<()> {
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir)?;
+    write_key(&dir.join(CLIENT_KEY_FILE), client_key)?;
+    write_key(&dir.join(SERVER_KEY_FILE), server_key)?;
+    write_key(&dir.join(PUBLIC_KEY_FILE), public_key)
+}
+
+/// Read back the keys written by [`save_keys`].
+pub fn load_keys(dir: impl AsRef<Path>) -> io::Result<(ClientKey, ServerKey, PublicKey)> {
+    let dir = dir.as_ref();
+    let client_key = read_key(&dir.join(CLIENT_KEY_FILE))?;
+    let server_key = read_key(&dir.join(SERVER_KEY_FILE))?;
+    let public_key = read_key(&dir.join(PUBLIC_KEY_FILE))?;
+    Ok((client_key, server_key, public_key))
+}
+
+/// Write the keys to `dir` like [`save_keys`], with the server key compressed.
+pub fn save_compressed_keys(
+    dir: impl AsRef<Path>,
+    client_key: &ClientKey,
+    server_key: &CompressedServerKey,
+    public_key: &PublicKey,
+) -> io::Result<()> {
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir)?;
+    write_key(&dir.join(CLIENT_KEY_FILE), client_key)?;
+    write_key(&dir.join(COMPRESSED_SERVER_KEY_FILE), server_key)?;
+    write_key(&dir.join(PUBLIC_KEY_FILE), public_key)
+}
+
+/// Read back the keys written by [`save_compressed_keys`], decompressing the
+/// server key.
+pub fn load_compressed_keys(dir: impl AsRef<Path>) -> io::Result<(ClientKey, ServerKey, PublicKey)> {
+    let dir = dir.as_ref();
+    let client_key = read_key(&dir.join(CLIENT_KEY_FILE))?;
+    let server_key: CompressedServerKey = read_key(&dir.join(COMPRESSED_SERVER_KEY_FILE))?;
+    let public_key = read_key(&dir.join(PUBLIC_KEY_FILE))?;
+    Ok((client_key, server_key.decompress(), public_key))
+}
+
+/// Read only the server-side keys written by [`save_keys`] or
+/// [`save_compressed_keys`], leaving the client key untouched.
+pub fn load_server_keys(dir: impl AsRef<Path>) -> io::Result<(ServerKey, PublicKey)> {
+    let dir = dir.as_ref();
+    let server_key = if dir.join(SERVER_KEY_FILE).is_file() {
+        read_key(&dir.join(SERVER_KEY_FILE))?
+    } else {
+        read_key::<CompressedServerKey>(&dir.join(COMPRESSED_SERVER_KEY_FILE))?.decompress()
+    };
+    let public_key = read_key(&dir.join(PUBLIC_KEY_FILE))?;
+    Ok((server_key, public_key))
+}
+
+/// Whether `dir` holds a full set of key files written by [`save_keys`].
+pub fn keys_exist(dir: impl AsRef<Path>) -> bool {
+    files_exist(dir.as_ref(), &[CLIENT_KEY_FILE, SERVER_KEY_FILE, PUBLIC_KEY_FILE])
+}
+
+/// Whether `dir` holds a full set of key files written by [`save_compressed_keys`].
+pub fn compressed_keys_exist(dir: impl AsRef<Path>) -> bool {
+    files_exist(dir.as_ref(), &[CLIENT_KEY_FILE, COMPRESSED_SERVER_KEY_FILE, PUBLIC_KEY_FILE])
+}
+
+fn files_exist(dir: &Path, files: &[&str]) -> bool {
+    files.iter().all(|file| dir.join(file).is_file())
+}
+
+/// Generate the client, server and public keys from `config`.
+pub fn generate_keys_with(config: FibConfig) -> (ClientKey, ServerKey, PublicKey) {
+    let (client_key, server_key) = generate_keys(config.build());
+    let public_key = PublicKey::new(&client_key);
+    (client_key, server_key, public_key)
+}
+
+/// [`generate_keys_with`], deterministically derived from `seed`: two calls with
+/// the same config and seed return the same keys, so a failing test can be
+/// replayed with them.
+///
+/// tfhe draws key material from the calling thread's engine, which is seeded for
+/// the duration of the call and reseeded from the OS afterwards so that later
+/// encryptions on this thread stay randomized. A seeded key is only as secret as
+/// its seed: never use it outside of tests.
+pub fn generate_keys_seeded(config: FibConfig, seed: u128) -> (ClientKey, ServerKey, PublicKey) {
+    let mut seeder = DeterministicSeeder::<DefaultRandomGenerator>::new(Seed(seed));
+    ShortintEngine::with_thread_local_mut(|engine| *engine = ShortintEngine::new_from_seeder(&mut seeder));
+    let _reseed = ReseedOnDrop;
+    generate_keys_with(config)
+}
+
+/// Replaces the seeded engine of [`generate_keys_seeded`] with an OS-seeded one.
+struct ReseedOnDrop;
+
+impl Drop for ReseedOnDrop {
+    fn drop(&mut self) {
+        ShortintEngine::with_thread_local_mut(|engine| *engine = ShortintEngine::new());
+    }
+}
+
+/// [`generate_keys_with`], the server key in compressed form.
+pub fn generate_compressed_keys(config: FibConfig) -> (ClientKey, CompressedServerKey, PublicKey) {
+    let client_key = ClientKey::generate(config.build());
+    let server_key = CompressedServerKey::new(&client_key);
+    let public_key = PublicKey::new(&client_key);
+    (client_key, server_key, public_key)
+}
+
+/// Load the keys from `dir` if present, otherwise generate them from `config` and
+/// save them there. Loaded keys keep the parameters they were generated with.
+pub fn load_or_generate_keys(
+    dir: impl AsRef<Path>,
+    config: FibConfig,
+) -> io::Result<(ClientKey, ServerKey, PublicKey)> {
+    let dir = dir.as_ref();
+    if keys_exist(dir) {
+        return load_keys(dir);
+    }
+    let (client_key, server_key, public_key) = generate_keys_with(config);
+    save_keys(dir, &client_key, &server_key, &public_key)?;
+    Ok((client_key, server_key, public_key))
+}
+
+/// [`load_or_generate_keys`] storing a compressed server key.
+pub fn load_or_generate_compressed_keys(
+    dir: impl AsRef<Path>,
+    config: FibConfig,
+) -> io::Result<(ClientKey, ServerKey, PublicKey)> {
+    let dir = dir.as_ref();
+    if compressed_keys_exist(dir) {
+        return load_compressed_keys(dir);
+    }
+    let (client_key, server_key, public_key) = generate_compressed_keys(config);
+    save_compressed_keys(dir, &client_key, &server_key, &public_key)?;
+    Ok((client_key, server_key.decompress(), public_key))
+}
+
+fn write_key<T>(path: &Path, key: &T) -> io::Result<()>
+where
+    T: serde::Serialize + tfhe::Versionize + tfhe::named::Named,
+{
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_versioned(key, &mut writer, KEY_SIZE_LIMIT)?;
+    writer.flush()
+}
+
+fn read_key<T>(path: &Path) -> io::Result<T>
+where
+    T: serde::de::DeserializeOwned + tfhe::Unversionize + tfhe::named::Named,
+{
+    read_versioned(BufReader::new(File::open(path)?), KEY_SIZE_LIMIT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serialization::write_ciphertext;
+    use crate::test_support::{Fixture, TestHarness};
+    use crate::{fibonacci_lookup_with_tables, FheUint16};
+    use tfhe::prelude::*;
+
+    #[test]
+    fn test_keys_round_trip() {
+        let dir = std::env::temp_dir().join(format!("fib-fhe-keys-{}", std::process::id()));
+        let harness = TestHarness::new();
+
+        save_keys(&dir, harness.client_key(), harness.server_key(), harness.pks()).unwrap();
+        assert!(keys_exist(&dir));
+        let loaded = Fixture::from_keys(load_keys(&dir).unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+
+        let loaded = loaded.harness();
+        let encrypted = loaded.encrypt(7);
+        let enc_lt = loaded.run(|| fibonacci_lookup_with_tables(&encrypted, loaded.indices(), loaded.fibs()));
+        let dec_lt: u16 = loaded.decrypt(&enc_lt);
+
+        assert_eq!(dec_lt, 13);
+    }
+
+    #[test]
+    fn test_seeded_keys() {
+        let key_bytes = |key: &ClientKey| {
+            let mut bytes = Vec::new();
+            write_versioned(key, &mut bytes, KEY_SIZE_LIMIT).unwrap();
+            bytes
+        };
+        let ciphertext_bytes = |ct: &FheUint16| {
+            let mut bytes = Vec::new();
+            write_ciphertext(ct, &mut bytes).unwrap();
+            bytes
+        };
+
+        // One set of keys alive at a time: the public and server keys are large.
+        let (original_key_bytes, encrypted, encrypted_with_pks) = {
+            let (client_key, _, pks) = generate_keys_seeded(FibConfig::default(), 42);
+            let encrypted = FheUint16::encrypt(21u16, &client_key);
+            // Encryption is randomized again once the keys are generated.
+            assert!(ciphertext_bytes(&encrypted) != ciphertext_bytes(&FheUint16::encrypt(21u16, &client_key)));
+            (key_bytes(&client_key), encrypted, FheUint16::encrypt(34u16, &pks))
+        };
+
+        let (replayed_client_key, _, _) = generate_keys_seeded(FibConfig::default(), 42);
+        assert!(key_bytes(&replayed_client_key) == original_key_bytes);
+        // The replayed keys decrypt what the original keys encrypted.
+        let decrypted: u16 = encrypted.decrypt(&replayed_client_key);
+        assert_eq!(decrypted, 21);
+        let decrypted: u16 = encrypted_with_pks.decrypt(&replayed_client_key);
+        assert_eq!(decrypted, 34);
+        drop(replayed_client_key);
+
+        let (other_client_key, _, _) = generate_keys_seeded(FibConfig::default(), 43);
+        assert!(key_bytes(&other_client_key) != original_key_bytes);
+    }
+
+    #[test]
+    fn test_compressed_keys_round_trip() {
+        let dir = std::env::temp_dir().join(format!("fib-fhe-compressed-keys-{}", std::process::id()));
+        // Only the uncompressed server key outlives the save: the loaded keys
+        // bring their own public key, and two of them do not fit in memory
+        // next to the shared test keys.
+        let server_key = {
+            let (client_key, compressed_server_key, pks) = generate_compressed_keys(FibConfig::default());
+            save_compressed_keys(&dir, &client_key, &compressed_server_key, &pks).unwrap();
+            ServerKey::new(&client_key)
+        };
+        assert!(compressed_keys_exist(&dir));
+        let loaded = Fixture::from_keys(load_compressed_keys(&dir).unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+
+        let loaded = loaded.harness();
+        let encrypted = loaded.encrypt(7);
+        let enc_uncompressed = crate::with_server_key(server_key, || {
+            fibonacci_lookup_with_tables(&encrypted, loaded.indices(), loaded.fibs())
+        });
+        let enc_compressed = loaded.run(|| fibonacci_lookup_with_tables(&encrypted, loaded.indices(), loaded.fibs()));
+
+        let dec_uncompressed: u16 = loaded.decrypt(&enc_uncompressed);
+        let dec_compressed: u16 = loaded.decrypt(&enc_compressed);
+        assert_eq!(dec_uncompressed, 13);
+        assert_eq!(dec_compressed, dec_uncompressed);
+    }
+}
@@ -0,0 +1,2347 @@
+//! Homomorphic Fibonacci over TFHE.
+//!
+//! Two strategies are provided to compute `F(n)` for an encrypted `n`:
+//! [`fibonacci_additions`] and [`fibonacci_lookup_with_tables`]. Both rely on the
+//! server key having been set with [`tfhe::set_server_key`] on the calling thread.
+//! That state is thread-local and shared with any other tfhe user on the thread;
+//! [`with_server_key`] scopes a key to a closure and restores the enclosing one.
+
+use num_traits::{PrimInt, WrappingAdd};
+use tfhe::prelude::*;
+use par::*;
+use serde::Serialize;
+use std::convert::Infallible;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+// rayon spawns threads, which `wasm32-unknown-unknown` does not have.
+#[cfg(all(target_arch = "wasm32", feature = "parallel"))]
+compile_error!("the default `parallel` feature does not build for wasm32, use `--no-default-features --features wasm`");
+
+#[cfg(feature = "async")]
+pub mod async_lookup;
+pub mod cli;
+pub mod client;
+#[cfg(feature = "compact")]
+pub mod compact;
+pub mod compression;
+pub mod context;
+pub mod csv_log;
+pub mod config;
+pub mod estimate;
+pub mod input;
+pub mod keys;
+pub mod negafibonacci;
+mod ops;
+mod par;
+pub mod pisano;
+#[cfg(feature = "profile")]
+pub mod profile;
+pub mod recurrence;
+pub mod run;
+pub mod segmented;
+pub mod sequence;
+pub mod serialization;
+pub mod server;
+#[cfg(feature = "stats")]
+pub mod stats;
+pub mod tables;
+#[cfg(test)]
+pub(crate) mod test_support;
+pub mod verify;
+
+use sequence::{build_encrypted_table, build_trivial_table, lookup_up_to, lookup_with_tables, Fibonacci, Lucas};
+
+pub use context::with_server_key;
+pub use tfhe;
+pub use tfhe::{FheBool, FheInt16, FheUint16, FheUint32, FheUint8, PublicKey, ServerKey};
+
+/// Maximum supported index for 16-bit Fibonacci; `F(25) = 75025` > `u16::MAX`.
+pub const MAX_FIBONACCI_INDEX: u16 = 24;
+
+/// Maximum supported index for 32-bit Fibonacci; `F(48) = 4807526976` > `u32::MAX`.
+pub const MAX_FIBONACCI_INDEX_U32: u32 = 47;
+
+/// Maximum index for [`fibonacci_composed`]; `F(9) = 34` > [`MAX_FIBONACCI_INDEX`].
+pub const MAX_COMPOSED_INDEX: u16 = 8;
+
+/// Maximum supported index for 8-bit Fibonacci; `F(14) = 377` > `u8::MAX`.
+pub const MAX_FIBONACCI_INDEX_U8: u8 = 13;
+
+/// Maximum supported index for saturating 16-bit Fibonacci; every entry past
+/// [`MAX_FIBONACCI_INDEX`] is `u16::MAX`.
+pub const MAX_SATURATING_INDEX: u16 = 31;
+
+/// Maximum supported index for [`fibonacci_prefix_sum`]; the sum up to 23 is
+/// `F(25) - 1 = 75024` > `u16::MAX`.
+pub const MAX_PREFIX_SUM_INDEX: u16 = MAX_FIBONACCI_INDEX - 2;
+
+/// Maximum supported index for 16-bit Lucas numbers; `L(24) = 103682` > `u16::MAX`.
+pub const MAX_LUCAS_INDEX: u16 = 23;
+
+/// Encrypted flag telling whether `n` equals entry `i` of `encrypted_indices`,
+/// the comparison every strategy selects its result with; any tfhe integer
+/// type is accepted, e.g. the `FheUint32` index tables of the `_u32` variants.
+///
+/// Requires the server key to be set on the calling thread.
+///
+/// # Panics
+///
+/// If `i` is past the end of `encrypted_indices`.
+pub fn index_matches<T>(n: &T, encrypted_indices: &[T], i: usize) -> FheBool
+where
+    T: for<'a> FheEq<&'a T>,
+{
+    let index = encrypted_indices.get(i).unwrap_or_else(|| {
+        panic!("index {} is past the end of a table of {} indices", i, encrypted_indices.len())
+    });
+    ops::eq(n, index)
+}
+
+/// Iterative homomorphic additions with encrypted index-selection.
+/// Builds encrypted indices internally, then iterates with homomorphic additions.
+///
+/// Always runs all `MAX_FIBONACCI_INDEX` iterations, so the server's work does
+/// not depend on the value of `n`. An `n` past [`MAX_FIBONACCI_INDEX`] matches no
+/// iteration and gives `F(0) = 0`, like every strategy of [`STRATEGIES`].
+/// Requires the server key to be set on the calling thread.
+pub fn fibonacci_additions(n: &FheUint16, pks: &PublicKey) -> FheUint16 {
+    fibonacci_additions_with(n, pks, |_| {})
+}
+
+/// [`fibonacci_additions`], also returning every `F(k)` the iteration passes
+/// through, still encrypted.
+///
+/// The first `MAX_FIBONACCI_INDEX + 1` entries are `F(0), ..., F(MAX_FIBONACCI_INDEX)`
+/// (they do not depend on `n`); the last one is the selected result `F(n)`. Meant
+/// for clients that want to decrypt the whole run. Requires the server key to be
+/// set on the calling thread.
+pub fn fibonacci_additions_trace(n: &FheUint16, pks: &PublicKey) -> Vec<FheUint16> {
+    let mut trace = Vec::with_capacity(usize::from(MAX_FIBONACCI_INDEX) + 2);
+    let result = fibonacci_additions_with(n, pks, |fib| trace.push(fib.clone()));
+    trace.push(result);
+    trace
+}
+
+/// [`fibonacci_additions`], checking `cancelled` between the iterations of its
+/// loop.
+///
+/// Returns [`Cancelled`] at the first iteration boundary after `cancelled` is
+/// set, e.g. by a Ctrl-C handler; an iteration that has started always runs to
+/// completion. Requires the server key to be set on the calling thread.
+pub fn fibonacci_additions_cancellable(
+    n: &FheUint16,
+    pks: &PublicKey,
+    cancelled: &AtomicBool,
+) -> Result<FheUint16, Cancelled> {
+    try_fibonacci_additions_with(n, pks, |_| {
+        if cancelled.load(Ordering::Relaxed) { Err(Cancelled) } else { Ok(()) }
+    })
+}
+
+/// A computation stopped early on request, see [`fibonacci_additions_cancellable`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// The additions loop, calling `on_term` with each `F(k)` in order.
+///
+/// `F(1)` is passed once the loop is set up and every later `F(k)` at the end
+/// of its iteration, so the time between two calls is one iteration.
+fn fibonacci_additions_with(n: &FheUint16, pks: &PublicKey, mut on_term: impl FnMut(&FheUint16)) -> FheUint16 {
+    let Ok(result) = try_fibonacci_additions_with(n, pks, |fib| {
+        on_term(fib);
+        Ok::<_, Infallible>(())
+    });
+    result
+}
+
+/// [`fibonacci_additions_with`], stopping at the first error of `on_term`.
+fn try_fibonacci_additions_with<E>(
+    n: &FheUint16,
+    pks: &PublicKey,
+    on_term: impl FnMut(&FheUint16) -> Result<(), E>,
+) -> Result<FheUint16, E> {
+    let encrypted_indices = build_encrypted_indices(pks);
+    additions_loop(n, &encrypted_indices, on_term)
+}
+
+/// The additions loop over any tfhe integer type, up to the last entry of
+/// `encrypted_indices`, whose first two entries double as `F(0)` and `F(1)`.
+fn additions_loop<T, E>(
+    n: &T,
+    encrypted_indices: &[T],
+    mut on_term: impl FnMut(&T) -> Result<(), E>,
+) -> Result<T, E>
+where
+    T: Clone + for<'a> FheEq<&'a T>,
+    for<'a> &'a T: std::ops::Add<&'a T, Output = T>,
+    FheBool: IfThenElse<T>,
+{
+    // Initialize result with F(1) if n == 1, F(0) otherwise: an n past the
+    // table matches no index and keeps F(0), as in the lookup.
+    let n_is_1 = index_matches(n, encrypted_indices, 1);
+    let mut a = encrypted_indices[0].clone();
+    let mut b = encrypted_indices[1].clone();
+    let mut result = ops::select(&n_is_1, &b, &a);
+    on_term(&a)?;
+    on_term(&b)?;
+
+    for i in 2..encrypted_indices.len() {
+        let next_fib = ops::add(&a, &b);
+        a = b;
+        b = next_fib.clone();
+        let n_is_i = index_matches(n, encrypted_indices, i);
+        // Use encrypted equality + select to multiplex the running result
+        // without data-dependent control flow.
+        result = ops::select(&n_is_i, &next_fib, &result);
+        on_term(&b)?;
+    }
+
+    Ok(result)
+}
+
+/// How [`fibonacci_table`] handles a term that does not fit its integer type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Overflow {
+    /// Panic on the first such term.
+    Checked,
+    /// Keep the term modulo `2^bits`, like the wrapping homomorphic additions.
+    Wrapping,
+    /// Clamp the term to the largest value of the type, e.g. `u16::MAX` past
+    /// `F(24)` in `u16`.
+    Saturating,
+}
+
+/// Build the plaintext table `F(0), ..., F(len - 1)` over any primitive integer,
+/// e.g. `fibonacci_table::<u32>(48, Overflow::Checked)` for the 32-bit range.
+///
+/// # Panics
+///
+/// With [`Overflow::Checked`], if `F(len - 1)` does not fit in `T`.
+pub fn fibonacci_table<T: PrimInt + WrappingAdd>(len: usize, overflow: Overflow) -> Vec<T> {
+    let mut fibs: Vec<T> = [T::zero(), T::one()].into_iter().take(len).collect();
+    while fibs.len() < len {
+        let (a, b) = (fibs[fibs.len() - 2], fibs[fibs.len() - 1]);
+        let next = match overflow {
+            Overflow::Checked => a.checked_add(&b).unwrap_or_else(|| {
+                panic!("F({}) does not fit in {}", fibs.len(), std::any::type_name::<T>())
+            }),
+            Overflow::Wrapping => a.wrapping_add(&b),
+            Overflow::Saturating => a.saturating_add(b),
+        };
+        fibs.push(next);
+    }
+    fibs
+}
+
+/// Build a plaintext Fibonacci table up to MAX_FIBONACCI_INDEX.
+///
+/// Entry `i` is `F(i)`, so the table has `MAX_FIBONACCI_INDEX + 1` elements.
+pub fn build_fibonacci_table_plain() -> Vec<u16> {
+    fibonacci_table(FIBONACCI_TABLE_LEN, Overflow::Checked)
+}
+
+/// Build encrypted indices with parallelization.
+///
+/// Entry `i` is an encryption of `i` for `i` in `0..=MAX_FIBONACCI_INDEX`.
+pub fn build_encrypted_indices(pks: &PublicKey) -> Vec<FheUint16> {
+    let indices = build_encrypted_indices_up_to(MAX_FIBONACCI_INDEX, pks);
+    debug_assert_eq!(indices.len(), FIBONACCI_TABLE_LEN);
+    indices
+}
+
+/// [`build_encrypted_indices`] stopping at `max_index`: `max_index + 1` entries,
+/// which cuts setup time when only small `n` are queried.
+///
+/// # Panics
+///
+/// If `max_index > MAX_FIBONACCI_INDEX`.
+pub fn build_encrypted_indices_up_to(max_index: u16, pks: &PublicKey) -> Vec<FheUint16> {
+    assert!(max_index <= MAX_FIBONACCI_INDEX, "max_index is at most {}", MAX_FIBONACCI_INDEX);
+    encrypt_with_progress((0..=max_index).collect(), pks, |_, _| {})
+}
+
+/// [`build_encrypted_indices`], calling `progress(done, total)` each time a
+/// ciphertext has been encrypted.
+pub fn build_encrypted_indices_with_progress(
+    pks: &PublicKey,
+    progress: impl Fn(usize, usize) + Sync,
+) -> Vec<FheUint16> {
+    encrypt_with_progress((0..=MAX_FIBONACCI_INDEX).collect(), pks, progress)
+}
+
+/// [`build_encrypted_indices`] encrypting with the client key instead of the
+/// public key.
+///
+/// Only for a server that legitimately holds the client key (single-tenant
+/// setups); the `fibonacci` bench compares the time and serialized size of both.
+pub fn build_encrypted_indices_with_client(client_key: &tfhe::ClientKey) -> Vec<FheUint16> {
+    (0..=MAX_FIBONACCI_INDEX)
+        .into_par_iter()
+        .map(|i| FheUint16::encrypt(i, client_key))
+        .collect()
+}
+
+/// Encrypt a batch of client inputs in parallel with the client key, mirroring
+/// [`build_encrypted_indices`]; the result feeds [`fibonacci_lookup_batch`].
+pub fn encrypt_inputs(values: &[u16], client_key: &tfhe::ClientKey) -> Vec<FheUint16> {
+    values
+        .par_iter()
+        .map(|value| FheUint16::encrypt(*value, client_key))
+        .collect()
+}
+
+/// Build encrypted Fibonacci table from plaintext with parallelization.
+///
+/// Entry `i` is an encryption of `F(i)`, aligned with [`build_encrypted_indices`].
+pub fn build_encrypted_fibs(pks: &PublicKey) -> Vec<FheUint16> {
+    let fibs = build_encrypted_fibs_up_to(MAX_FIBONACCI_INDEX, pks);
+    debug_assert_eq!(fibs.len(), FIBONACCI_TABLE_LEN);
+    fibs
+}
+
+/// [`build_encrypted_fibs`] stopping at `F(max_index)`, aligned with
+/// [`build_encrypted_indices_up_to`].
+///
+/// # Panics
+///
+/// If `max_index > MAX_FIBONACCI_INDEX`.
+pub fn build_encrypted_fibs_up_to(max_index: u16, pks: &PublicKey) -> Vec<FheUint16> {
+    assert!(max_index <= MAX_FIBONACCI_INDEX, "max_index is at most {}", MAX_FIBONACCI_INDEX);
+    let mut table = build_fibonacci_table_plain();
+    table.truncate(usize::from(max_index) + 1);
+    encrypt_with_progress(table, pks, |_, _| {})
+}
+
+/// [`build_encrypted_fibs`], calling `progress(done, total)` each time a
+/// ciphertext has been encrypted.
+pub fn build_encrypted_fibs_with_progress(
+    pks: &PublicKey,
+    progress: impl Fn(usize, usize) + Sync,
+) -> Vec<FheUint16> {
+    encrypt_with_progress(build_fibonacci_table_plain(), pks, progress)
+}
+
+/// Encrypt `values` in parallel, reporting the number of completed encryptions.
+///
+/// Encryptions finish out of order, so completions are counted with an atomic
+/// and `progress` may be called concurrently from several threads.
+pub(crate) fn encrypt_with_progress(
+    values: Vec<u16>,
+    pks: &PublicKey,
+    progress: impl Fn(usize, usize) + Sync,
+) -> Vec<FheUint16> {
+    let total = values.len();
+    let done = AtomicUsize::new(0);
+    values
+        .into_par_iter()
+        .map(|v| {
+            let ct = FheUint16::encrypt(v, pks);
+            progress(done.fetch_add(1, Ordering::Relaxed) + 1, total);
+            ct
+        })
+        .collect()
+}
+
+/// Build indices `0..=MAX_FIBONACCI_INDEX` as trivial ciphertexts.
+///
+/// Trivial ciphertexts are not encrypted at all: this mode is insecure and only
+/// meant for testing the eq/select logic without the cost of real encryption.
+/// Requires the server key to be set on the calling thread.
+pub fn build_trivial_indices() -> Vec<FheUint16> {
+    (0..=MAX_FIBONACCI_INDEX).map(FheUint16::encrypt_trivial).collect()
+}
+
+/// Build the Fibonacci table as trivial ciphertexts; insecure and test-only, like
+/// [`build_trivial_indices`].
+pub fn build_trivial_fibs() -> Vec<FheUint16> {
+    build_trivial_table::<Fibonacci>()
+}
+
+/// Lookup over an encrypted table
+/// equality + select, reusing prebuilt tables.
+///
+/// `encrypted_indices` and `encrypted_fibs` are the tables returned by
+/// [`build_encrypted_indices`] and [`build_encrypted_fibs`]; both can be reused
+/// across queries. Every entry is compared and selected whatever `n` is, so the
+/// server's work does not depend on its value; there is no early exit on a
+/// match. Requires the server key to be set on the calling thread.
+///
+/// Debug builds assert that the tables pass [`check_tables`];
+/// [`try_fibonacci_lookup_with_tables`] returns the mismatch as an error instead.
+pub fn fibonacci_lookup_with_tables(
+    n: &FheUint16,
+    encrypted_indices: &[FheUint16],
+    encrypted_fibs: &[FheUint16],
+) -> FheUint16 {
+    debug_assert_eq!(check_tables(encrypted_indices, encrypted_fibs), Ok(()));
+    lookup_with_tables::<Fibonacci>(n, encrypted_indices, encrypted_fibs)
+}
+
+/// [`fibonacci_lookup_with_tables`] validating the tables up front, for tables
+/// that come from outside the process (e.g. loaded from disk).
+pub fn try_fibonacci_lookup_with_tables(
+    n: &FheUint16,
+    encrypted_indices: &[FheUint16],
+    encrypted_fibs: &[FheUint16],
+) -> Result<FheUint16, TableMismatch> {
+    check_tables(encrypted_indices, encrypted_fibs)?;
+    Ok(lookup_with_tables::<Fibonacci>(n, encrypted_indices, encrypted_fibs))
+}
+
+/// Check that the index and Fibonacci tables both hold [`FIBONACCI_TABLE_LEN`]
+/// entries, as built by [`build_encrypted_indices`] and [`build_encrypted_fibs`].
+///
+/// Only the lengths can be checked: whether entry `i` really encrypts `i` and
+/// `F(i)` is hidden from the server.
+pub fn check_tables(encrypted_indices: &[FheUint16], encrypted_fibs: &[FheUint16]) -> Result<(), TableMismatch> {
+    if encrypted_indices.len() == FIBONACCI_TABLE_LEN && encrypted_fibs.len() == FIBONACCI_TABLE_LEN {
+        Ok(())
+    } else {
+        Err(TableMismatch { indices: encrypted_indices.len(), fibs: encrypted_fibs.len() })
+    }
+}
+
+/// Lookup tables of the wrong lengths, see [`check_tables`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TableMismatch {
+    pub indices: usize,
+    pub fibs: usize,
+}
+
+impl std::fmt::Display for TableMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "expected {} indices and {} Fibonacci numbers, got {} and {}",
+            FIBONACCI_TABLE_LEN, FIBONACCI_TABLE_LEN, self.indices, self.fibs
+        )
+    }
+}
+
+impl std::error::Error for TableMismatch {}
+
+/// [`fibonacci_lookup_with_tables`] over tables built by
+/// [`build_encrypted_indices_up_to`] and [`build_encrypted_fibs_up_to`] with the
+/// same `max_index`.
+///
+/// `n > max_index` matches no entry and falls through to `F(0) = 0`, as past
+/// the full table. Requires the server key to be set on the calling thread.
+///
+/// # Panics
+///
+/// If `max_index > MAX_FIBONACCI_INDEX` or a table is shorter than `max_index + 1`.
+pub fn fibonacci_lookup_up_to(
+    n: &FheUint16,
+    encrypted_indices: &[FheUint16],
+    encrypted_fibs: &[FheUint16],
+    max_index: u16,
+) -> FheUint16 {
+    assert!(max_index <= MAX_FIBONACCI_INDEX, "max_index is at most {}", MAX_FIBONACCI_INDEX);
+    lookup_up_to(n, encrypted_indices, encrypted_fibs, usize::from(max_index))
+}
+
+/// Lookup of `F(n)` for a plaintext `n`: the index is public and only the table
+/// values are secret, so this is a plain clone of `encrypted_fibs[n]`.
+///
+/// No homomorphic operation runs, which makes it vastly faster than the
+/// encrypted-index path of [`fibonacci_lookup_with_tables`] (24 comparisons and
+/// 24 selects) and means it needs no server key. Only use it for clients that
+/// need the sequence values kept confidential but not the index.
+///
+/// # Panics
+///
+/// If `n` is past the end of `encrypted_fibs`.
+pub fn fibonacci_lookup_plain_index(n: u16, encrypted_fibs: &[FheUint16]) -> FheUint16 {
+    encrypted_fibs[usize::from(n)].clone()
+}
+
+/// Lookup comparing `n` against each index as a plaintext scalar.
+///
+/// The indices of the table are public constants, so the scalar [`FheEq::eq`]
+/// against `i` replaces the comparison with an encrypted index: each one is
+/// cheaper, and no index table has to be built, encrypted or stored. The
+/// result is the same as [`fibonacci_lookup_with_tables`]: every entry is
+/// selected whatever `n` is, and `n` past `encrypted_fibs` falls through to
+/// `F(0)`. Requires the server key to be set on the calling thread.
+pub fn fibonacci_lookup_scalar_index(n: &FheUint16, encrypted_fibs: &[FheUint16]) -> FheUint16 {
+    let mut result = encrypted_fibs[0].clone();
+    for (i, fib) in (0u16..).zip(encrypted_fibs).skip(1) {
+        let is_match = ops::scalar_eq(n, i);
+        result = ops::select(&is_match, fib, &result);
+    }
+    result
+}
+
+/// Debug helper: how many entries of `encrypted_indices` are equal to `n`.
+///
+/// Decrypts every equality flag of the lookup with the client key, so it must
+/// never run server-side; it only exists in tests and with the `debug` feature.
+/// A well-formed index table gives exactly 1 for `n <= MAX_FIBONACCI_INDEX` and 0
+/// past it. Requires the server key to be set on the calling thread.
+#[cfg(any(test, feature = "debug"))]
+pub fn count_matches(n: &FheUint16, encrypted_indices: &[FheUint16], client_key: &tfhe::ClientKey) -> u16 {
+    encrypted_indices
+        .iter()
+        .map(|index| u16::from(n.eq(index).decrypt(client_key)))
+        .sum()
+}
+
+/// Number of entries of the full Fibonacci tables, `0..=MAX_FIBONACCI_INDEX`.
+pub const FIBONACCI_TABLE_LEN: usize = MAX_FIBONACCI_INDEX as usize + 1;
+
+/// Array counterpart of [`build_encrypted_indices`] for the first `N` indices,
+/// with the table size known at compile time.
+///
+/// # Panics
+///
+/// If `N` is 0 or larger than [`FIBONACCI_TABLE_LEN`].
+pub fn build_encrypted_indices_array<const N: usize>(pks: &PublicKey) -> [FheUint16; N] {
+    assert!((1..=FIBONACCI_TABLE_LEN).contains(&N), "table length must be in 1..={}", FIBONACCI_TABLE_LEN);
+    let indices: Vec<FheUint16> = (0..N as u16)
+        .into_par_iter()
+        .map(|i| FheUint16::encrypt(i, pks))
+        .collect();
+    indices.try_into().unwrap_or_else(|_| unreachable!("collected N indices"))
+}
+
+/// Array counterpart of [`build_encrypted_fibs`] for `F(0), ..., F(N - 1)`.
+///
+/// # Panics
+///
+/// If `N` is 0 or larger than [`FIBONACCI_TABLE_LEN`].
+pub fn build_encrypted_fibs_array<const N: usize>(pks: &PublicKey) -> [FheUint16; N] {
+    assert!((1..=FIBONACCI_TABLE_LEN).contains(&N), "table length must be in 1..={}", FIBONACCI_TABLE_LEN);
+    let fibs: Vec<FheUint16> = build_fibonacci_table_plain()[..N]
+        .par_iter()
+        .copied()
+        .map(|v| FheUint16::encrypt(v, pks))
+        .collect();
+    fibs.try_into().unwrap_or_else(|_| unreachable!("collected N values"))
+}
+
+/// [`fibonacci_lookup_with_tables`] over fixed-size arrays; both tables have `N`
+/// entries by construction, so the loop needs no bounds checks.
+///
+/// Requires the server key to be set on the calling thread.
+pub fn fibonacci_lookup_array<const N: usize>(
+    n: &FheUint16,
+    encrypted_indices: &[FheUint16; N],
+    encrypted_fibs: &[FheUint16; N],
+) -> FheUint16 {
+    let mut result = encrypted_fibs[0].clone();
+    for (i, fib) in encrypted_fibs.iter().enumerate().skip(1) {
+        let is_match = index_matches(n, encrypted_indices, i);
+        result = ops::select(&is_match, fib, &result);
+    }
+    result
+}
+
+/// [`fibonacci_lookup_with_tables`] plus an encrypted `out_of_range` flag.
+///
+/// No index matches when `n > MAX_FIBONACCI_INDEX`, so the lookup falls through
+/// to `F(0)`. The flag, computed as `n > encrypted_indices[MAX_FIBONACCI_INDEX]`,
+/// lets the client tell such a query apart from a genuine `F(0)` after
+/// decryption.
+pub fn fibonacci_lookup_checked(
+    n: &FheUint16,
+    encrypted_indices: &[FheUint16],
+    encrypted_fibs: &[FheUint16],
+) -> (FheUint16, FheBool) {
+    let result = fibonacci_lookup_with_tables(n, encrypted_indices, encrypted_fibs);
+    let out_of_range = ops::gt(n, &encrypted_indices[usize::from(MAX_FIBONACCI_INDEX)]);
+    (result, out_of_range)
+}
+
+/// `F(F(n))`: the looked-up `F(n)` is used as an index into the same tables.
+///
+/// Only meaningful for `n <= MAX_COMPOSED_INDEX`: past it, `F(n) > 24` matches no
+/// index and the result falls through to `F(0) = 0`, as does any
+/// `n > MAX_FIBONACCI_INDEX`; [`fibonacci_composed_checked`] flags both cases.
+/// Costs two lookups. Requires the server key to be set on the calling thread.
+pub fn fibonacci_composed(
+    n: &FheUint16,
+    encrypted_indices: &[FheUint16],
+    encrypted_fibs: &[FheUint16],
+) -> FheUint16 {
+    let inner = fibonacci_lookup_with_tables(n, encrypted_indices, encrypted_fibs);
+    fibonacci_lookup_with_tables(&inner, encrypted_indices, encrypted_fibs)
+}
+
+/// [`fibonacci_composed`] plus the [`fibonacci_lookup_checked`] flags of both
+/// lookups, ORed: set when `n` or the intermediate `F(n)` exceeds
+/// [`MAX_FIBONACCI_INDEX`], i.e. whenever `n > MAX_COMPOSED_INDEX`. Checking
+/// the intermediate alone misses `n > MAX_FIBONACCI_INDEX`, which looks up as
+/// `F(0) = 0`, an index in the table.
+pub fn fibonacci_composed_checked(
+    n: &FheUint16,
+    encrypted_indices: &[FheUint16],
+    encrypted_fibs: &[FheUint16],
+) -> (FheUint16, FheBool) {
+    let (inner, n_out_of_range) = fibonacci_lookup_checked(n, encrypted_indices, encrypted_fibs);
+    let (result, inner_out_of_range) = fibonacci_lookup_checked(&inner, encrypted_indices, encrypted_fibs);
+    (result, n_out_of_range | inner_out_of_range)
+}
+
+/// Whether `F(n) > F(m)`, as an encrypted boolean.
+///
+/// Compares the looked-up values rather than the indices: the sequence only
+/// increases strictly from `F(2)` on, and `F(1) = F(2)` is not greater than
+/// either. An index past [`MAX_FIBONACCI_INDEX`] looks up as `F(0) = 0`. Costs two
+/// lookups and one comparison. Requires the server key to be set on the calling
+/// thread.
+pub fn fibonacci_value_gt(
+    n: &FheUint16,
+    m: &FheUint16,
+    encrypted_indices: &[FheUint16],
+    encrypted_fibs: &[FheUint16],
+) -> FheBool {
+    let fib_n = fibonacci_lookup_with_tables(n, encrypted_indices, encrypted_fibs);
+    let fib_m = fibonacci_lookup_with_tables(m, encrypted_indices, encrypted_fibs);
+    ops::gt(&fib_n, &fib_m)
+}
+
+/// The encrypted `min(n, max_encrypted)`, so that an index past the table is
+/// treated as its last entry. Requires the server key to be set on the calling
+/// thread.
+pub fn clamp_index(n: &FheUint16, max_encrypted: &FheUint16) -> FheUint16 {
+    n.min(max_encrypted)
+}
+
+/// [`fibonacci_lookup_with_tables`] on the index clamped to
+/// `MAX_FIBONACCI_INDEX`: `n = 30` gives `F(24)` instead of falling through to
+/// `F(0)`.
+///
+/// The bound is the last entry of `encrypted_indices`, so it stays encrypted
+/// like every other table entry. Requires the server key to be set on the
+/// calling thread.
+pub fn fibonacci_lookup_clamped(
+    n: &FheUint16,
+    encrypted_indices: &[FheUint16],
+    encrypted_fibs: &[FheUint16],
+) -> FheUint16 {
+    let clamped = clamp_index(n, &encrypted_indices[usize::from(MAX_FIBONACCI_INDEX)]);
+    fibonacci_lookup_with_tables(&clamped, encrypted_indices, encrypted_fibs)
+}
+
+/// `F(0) + F(1) + ... + F(n)`, computed as `F(n + 2) - 1` with one lookup.
+///
+/// Only meaningful for `n <= MAX_PREFIX_SUM_INDEX`. Past it, the lookup reads
+/// index `n + 2 mod 2^16`, like any `u16` addition: up to `n = u16::MAX - 2` it
+/// matches no index and the result is `F(0) - 1`, wrapped to `u16::MAX`.
+/// `n = u16::MAX - 1` wraps to index 0, which gives the same `F(0) - 1`, and
+/// `n = u16::MAX` wraps to index 1, which gives `F(1) - 1 = 0`. Use
+/// [`fibonacci_prefix_sum_checked`] to tell out-of-range inputs apart. Requires
+/// the server key to be set on the calling thread.
+pub fn fibonacci_prefix_sum(
+    n: &FheUint16,
+    encrypted_indices: &[FheUint16],
+    encrypted_fibs: &[FheUint16],
+) -> FheUint16 {
+    let shifted = ops::scalar_add(n, 2u16);
+    ops::scalar_sub(&fibonacci_lookup_with_tables(&shifted, encrypted_indices, encrypted_fibs), 1u16)
+}
+
+/// [`fibonacci_prefix_sum`] plus an encrypted `out_of_range` flag, set when
+/// `n > MAX_PREFIX_SUM_INDEX`, whatever the wrapped sum came out as.
+///
+/// The flag is a scalar comparison of `n` itself, so it does not depend on how
+/// `n + 2` wrapped. Requires the server key to be set on the calling thread.
+pub fn fibonacci_prefix_sum_checked(
+    n: &FheUint16,
+    encrypted_indices: &[FheUint16],
+    encrypted_fibs: &[FheUint16],
+) -> (FheUint16, FheBool) {
+    let sum = fibonacci_prefix_sum(n, encrypted_indices, encrypted_fibs);
+    let out_of_range = ops::gt(n, MAX_PREFIX_SUM_INDEX);
+    (sum, out_of_range)
+}
+
+/// Sum of the terms `F(i)` whose `mask[i]` is set, e.g. `F(5) + F(7) + F(9)`.
+///
+/// The mask is plaintext: unselected terms are skipped rather than selected
+/// homomorphically, so the cost is one addition per selected term past the
+/// first. Mask entries past the table are ignored, and an empty selection gives
+/// an encrypted 0. The sum wraps past `u16::MAX`: selecting every term of the
+/// table already gives `F(26) - 1 = 121392`. Requires the server key to be set on
+/// the calling thread.
+pub fn fibonacci_masked_sum(mask: &[bool], encrypted_fibs: &[FheUint16]) -> FheUint16 {
+    let mut selected = mask.iter().zip(encrypted_fibs).filter(|(keep, _)| **keep).map(|(_, fib)| fib);
+    let Some(first) = selected.next() else {
+        return FheUint16::encrypt_trivial(0u16);
+    };
+    selected.fold(first.clone(), |sum, fib| ops::add(&sum, fib))
+}
+
+/// `F(n) mod m` for a plaintext modulus `m`: a lookup followed by a scalar
+/// remainder.
+///
+/// Past [`MAX_FIBONACCI_INDEX`] the result is `F(0) mod m = 0`; computing further
+/// needs a table reduced mod `m` instead. Requires the server key to be set on
+/// the calling thread.
+///
+/// # Panics
+///
+/// If `m == 0`.
+pub fn fibonacci_mod(
+    n: &FheUint16,
+    encrypted_indices: &[FheUint16],
+    encrypted_fibs: &[FheUint16],
+    m: u16,
+) -> FheUint16 {
+    assert_ne!(m, 0, "modulus must be non-zero");
+    fibonacci_lookup_with_tables(n, encrypted_indices, encrypted_fibs) % m
+}
+
+/// Sum of the decimal digits of `F(n)`, e.g. 10 for `F(10) = 55`.
+///
+/// Every `F(n)` of the table has at most 5 digits (`F(24) = 46368`): after the
+/// lookup, 4 scalar `div_rem` by 10 peel off the low digits and the last quotient
+/// is the leading one, each digit then costing one addition. The divisions
+/// dominate: tfhe computes each as a multiplication by a precomputed inverse
+/// followed by shifts, so every digit costs far more than an `eq` + `select` of
+/// the lookup. Past [`MAX_FIBONACCI_INDEX`] the result is the digit sum of
+/// `F(0)`, 0. Requires the server key to be set on the calling thread.
+pub fn fibonacci_digit_sum(
+    n: &FheUint16,
+    encrypted_indices: &[FheUint16],
+    encrypted_fibs: &[FheUint16],
+) -> FheUint16 {
+    let mut value = fibonacci_lookup_with_tables(n, encrypted_indices, encrypted_fibs);
+    let mut sum = FheUint16::encrypt_trivial(0u16);
+    for _ in 0..4 {
+        let (quotient, digit) = ops::scalar_div_rem(&value, 10u16);
+        sum = ops::add(&sum, &digit);
+        value = quotient;
+    }
+    ops::add(&sum, &value)
+}
+
+/// Inverse lookup: the encrypted index `n` such that `F(n) = v`, or 0 when `v`
+/// is not in the table.
+///
+/// Compares `v` against every entry of `encrypted_fibs` and selects the matching
+/// entry of `encrypted_indices`. `F(1) = F(2) = 1`, and the later match wins, so
+/// `v = 1` gives 2. The sentinel 0 is also the genuine index of `v = 0`.
+/// Requires the server key to be set on the calling thread.
+pub fn fibonacci_index_of(
+    v: &FheUint16,
+    encrypted_indices: &[FheUint16],
+    encrypted_fibs: &[FheUint16],
+) -> FheUint16 {
+    let mut result = encrypted_indices[0].clone();
+    for (i, index) in encrypted_indices.iter().enumerate().skip(1) {
+        let is_match = ops::eq(v, &encrypted_fibs[i]);
+        result = ops::select(&is_match, index, &result);
+    }
+    result
+}
+
+/// Encrypted flag telling whether `v` is one of `F(0), ..., F(MAX_FIBONACCI_INDEX)`.
+///
+/// Membership only, unlike [`fibonacci_index_of`]: ORs the equality flags of `v`
+/// against every entry of `encrypted_fibs`, so `v = 1`, which matches both
+/// `F(1)` and `F(2)`, is simply set. Requires the server key to be set on the
+/// calling thread.
+pub fn is_fibonacci_number(v: &FheUint16, encrypted_fibs: &[FheUint16]) -> FheBool {
+    encrypted_fibs
+        .iter()
+        .map(|fib| ops::eq(v, fib))
+        .reduce(|acc, is_match| acc | is_match)
+        .unwrap_or_else(|| FheBool::encrypt_trivial(false))
+}
+
+/// How many entries of `encrypted_fibs` are strictly less than `v`: the
+/// position of `v` in the sequence, as an encrypted rank.
+///
+/// Sums the `lt` flags of every entry, cast to `FheUint16`. `F(1) = F(2) = 1`
+/// are counted twice, so `v = 22`, above `0, 1, 1, 2, 3, 5, 8, 13, 21`, gives 9.
+/// Requires the server key to be set on the calling thread.
+pub fn count_fibs_below(v: &FheUint16, encrypted_fibs: &[FheUint16]) -> FheUint16 {
+    encrypted_fibs
+        .iter()
+        .map(|fib| FheUint16::cast_from(ops::lt(fib, v)))
+        .reduce(|count, is_below| ops::add(&count, &is_below))
+        .unwrap_or_else(|| FheUint16::encrypt_trivial(0u16))
+}
+
+/// Range query: the full table with every `F(i)` outside `lo <= i <= hi`
+/// replaced by an encrypted zero.
+///
+/// The result always has one entry per table index, so its length reveals
+/// nothing about `lo` and `hi`; the client decrypts it and keeps the entries of
+/// its range. `lo > hi` masks every entry. `F(0) = 0` is indistinguishable from
+/// a masked entry. Requires the server key to be set on the calling thread.
+pub fn fibonacci_range(
+    lo: &FheUint16,
+    hi: &FheUint16,
+    encrypted_indices: &[FheUint16],
+    encrypted_fibs: &[FheUint16],
+) -> Vec<FheUint16> {
+    let zero = FheUint16::encrypt_trivial(0u16);
+    encrypted_indices
+        .iter()
+        .zip(encrypted_fibs)
+        .map(|(index, fib)| {
+            let in_range = ops::ge(index, lo) & ops::le(index, hi);
+            ops::select(&in_range, fib, &zero)
+        })
+        .collect()
+}
+
+/// The consecutive pair `(F(n), F(n+1))` in one pass over the tables, so a client
+/// can approximate the golden ratio `F(n+1) / F(n)` locally.
+///
+/// Each equality flag selects both the matching entry and the one after it.
+/// `F(MAX_FIBONACCI_INDEX + 1)` overflows `u16`, so `n = MAX_FIBONACCI_INDEX`
+/// yields the flag value `u16::MAX` as its second element, like
+/// [`fibonacci_lookup_saturating`]. An `n` past the table yields `(F(0), F(1))`,
+/// as `n = 0` does. Requires the server key to be set on the calling thread.
+pub fn fibonacci_consecutive_pair(
+    n: &FheUint16,
+    encrypted_indices: &[FheUint16],
+    encrypted_fibs: &[FheUint16],
+) -> (FheUint16, FheUint16) {
+    select_consecutive_pair(n, encrypted_indices, encrypted_fibs, FheUint16::encrypt_trivial(u16::MAX))
+}
+
+/// `(F(n), F(n+1))` selected in one pass, with `overflow` standing for
+/// `F(MAX_FIBONACCI_INDEX + 1)`; `(F(0), F(1))` when `n` matches no index past
+/// `0`.
+fn select_consecutive_pair(
+    n: &FheUint16,
+    encrypted_indices: &[FheUint16],
+    encrypted_fibs: &[FheUint16],
+    overflow: FheUint16,
+) -> (FheUint16, FheUint16) {
+    let nexts = encrypted_fibs.iter().skip(1).chain([&overflow]);
+
+    let mut current = encrypted_fibs[0].clone();
+    let mut next = encrypted_fibs[1].clone();
+    for (i, (fib, fib_next)) in encrypted_fibs.iter().zip(nexts).enumerate().skip(1) {
+        let is_match = index_matches(n, encrypted_indices, i);
+        current = ops::select(&is_match, fib, &current);
+        next = ops::select(&is_match, fib_next, &next);
+    }
+    (current, next)
+}
+
+/// `F(n+1)` for the encrypted index `n`, without revealing `n`.
+///
+/// Each entry of `encrypted_indices` selects the entry after it in
+/// `encrypted_fibs`. `F(MAX_FIBONACCI_INDEX + 1) = 75025` overflows `u16`, so
+/// `n = MAX_FIBONACCI_INDEX` has nothing to select and falls through to `0`, like
+/// an index past the table in [`fibonacci_lookup_with_tables`]. Use
+/// [`fibonacci_consecutive_pair`] to tell that case apart. Requires the server key
+/// to be set on the calling thread.
+pub fn fibonacci_next(n: &FheUint16, encrypted_indices: &[FheUint16], encrypted_fibs: &[FheUint16]) -> FheUint16 {
+    let mut result = FheUint16::encrypt_trivial(0u16);
+    for (i, fib_next) in encrypted_fibs.iter().skip(1).enumerate() {
+        let is_match = index_matches(n, encrypted_indices, i);
+        result = ops::select(&is_match, fib_next, &result);
+    }
+    result
+}
+
+/// `F(n+1) - F(n)` for the encrypted index `n`, which by the Fibonacci recurrence
+/// is `F(n-1)`: a cross-check of the table against itself.
+///
+/// Both values are selected in one pass, like [`fibonacci_consecutive_pair`],
+/// and subtracted with wrapping. `F(MAX_FIBONACCI_INDEX + 1)` is taken as
+/// `F(23) + F(24)` wrapped to `u16`; the wrapping cancels in the subtraction, so
+/// `n = MAX_FIBONACCI_INDEX` still yields `F(23)`. `n = 0` yields `1`, the
+/// conventional `F(-1)`, and so does an `n` past the table, which selects the
+/// same pair `(F(0), F(1))`. Requires the server key to be set on the calling
+/// thread.
+pub fn fibonacci_diff(n: &FheUint16, encrypted_indices: &[FheUint16], encrypted_fibs: &[FheUint16]) -> FheUint16 {
+    let last = encrypted_fibs.len() - 1;
+    let overflow = ops::add(&encrypted_fibs[last - 1], &encrypted_fibs[last]);
+    let (current, next) = select_consecutive_pair(n, encrypted_indices, encrypted_fibs, overflow);
+    ops::sub(&next, &current)
+}
+
+/// Encrypted flag telling whether `F(n)` is even: every third Fibonacci number,
+/// i.e. exactly when `n` is a multiple of 3.
+///
+/// Looks up `F(n)` and tests its low bit with a scalar bitand. Requires the
+/// server key to be set on the calling thread.
+pub fn fibonacci_is_even(
+    n: &FheUint16,
+    encrypted_indices: &[FheUint16],
+    encrypted_fibs: &[FheUint16],
+) -> FheBool {
+    let fib = fibonacci_lookup_with_tables(n, encrypted_indices, encrypted_fibs);
+    ops::scalar_eq(&(&fib & 1u16), 0u16)
+}
+
+/// Encrypted flag telling whether `F(m) == F(n)`, which for `m != n` only
+/// happens with `F(1) = F(2) = 1`.
+///
+/// Looks up both values and compares them, so the server learns neither the
+/// indices nor whether they are equal. Requires the server key to be set on the
+/// calling thread.
+pub fn fibonacci_values_equal(
+    m: &FheUint16,
+    n: &FheUint16,
+    encrypted_indices: &[FheUint16],
+    encrypted_fibs: &[FheUint16],
+) -> FheBool {
+    let fib_m = fibonacci_lookup_with_tables(m, encrypted_indices, encrypted_fibs);
+    let fib_n = fibonacci_lookup_with_tables(n, encrypted_indices, encrypted_fibs);
+    ops::eq(&fib_m, &fib_n)
+}
+
+/// `F(n) + F(m)` for two encrypted indices in one round-trip, with an encrypted
+/// flag set when the sum overflowed `u16`.
+///
+/// The sum wraps on overflow, e.g. `F(24) + F(23) = 75025` comes out as
+/// `75025 - 65536 = 9489` with the flag set; `F(23) + F(23) = 57314` still fits.
+/// Requires the server key to be set on the calling thread.
+pub fn fibonacci_sum_of_two(
+    n: &FheUint16,
+    m: &FheUint16,
+    encrypted_indices: &[FheUint16],
+    encrypted_fibs: &[FheUint16],
+) -> (FheUint16, FheBool) {
+    let fib_n = fibonacci_lookup_with_tables(n, encrypted_indices, encrypted_fibs);
+    let fib_m = fibonacci_lookup_with_tables(m, encrypted_indices, encrypted_fibs);
+    ops::overflowing_add(&fib_n, &fib_m)
+}
+
+/// `F(n)` when `cond` is true, `F(m)` otherwise, all three encrypted.
+///
+/// The index is selected first and looked up once, which costs one select on
+/// top of a single lookup instead of two lookups and a select on their
+/// results. Requires the server key to be set on the calling thread.
+pub fn fibonacci_select_index(
+    cond: &FheBool,
+    n: &FheUint16,
+    m: &FheUint16,
+    encrypted_indices: &[FheUint16],
+    encrypted_fibs: &[FheUint16],
+) -> FheUint16 {
+    let index = ops::select(cond, n, m);
+    fibonacci_lookup_with_tables(&index, encrypted_indices, encrypted_fibs)
+}
+
+/// Zeckendorf representation of the plaintext `target`: entry `i` of the mask is
+/// an encrypted flag set when `F(i)` is one of the non-consecutive Fibonacci
+/// numbers summing to `target`, e.g. `17 = F(7) + F(4) + F(2) = 13 + 3 + 1`.
+///
+/// Runs the greedy algorithm over `encrypted_fibs` (the [`build_encrypted_fibs`]
+/// table) from the largest entry down: an entry is used when it fits in what is
+/// left of `target`, which is then reduced by a `select`. Only `target` is known
+/// to the server, the comparisons are homomorphic. `F(0)` and `F(1)` are never
+/// used, since `F(1) = F(2)`, so their flags are trivially false. Every `u16`
+/// target is covered: it is below `F(25) = 75025`, so its representation only
+/// uses terms up to `F(24)`, the last entry of the table. Requires the server
+/// key to be set on the calling thread.
+pub fn zeckendorf_mask(target: u16, encrypted_fibs: &[FheUint16]) -> Vec<FheBool> {
+    let mut mask = vec![FheBool::encrypt_trivial(false); encrypted_fibs.len()];
+    let mut remaining = FheUint16::encrypt_trivial(target);
+    for (used, fib) in mask.iter_mut().zip(encrypted_fibs).skip(2).rev() {
+        let fits = ops::le(fib, &remaining);
+        remaining = ops::select(&fits, &ops::sub(&remaining, fib), &remaining);
+        *used = fits;
+    }
+    mask
+}
+
+/// Indices `n <= MAX_FIBONACCI_INDEX` for which `F(n)` is prime: 2, 3, 5, 13, 89,
+/// 233, 1597 and 28657.
+pub const PRIME_FIBONACCI_INDICES: [u16; 8] = [3, 4, 5, 7, 11, 13, 17, 23];
+
+/// Encrypted flag telling whether `F(n)` is prime, without computing `F(n)`.
+///
+/// ORs the equality flags of `n` against the [`PRIME_FIBONACCI_INDICES`] entries
+/// of `encrypted_indices`. Requires the server key to be set on the calling
+/// thread.
+pub fn fibonacci_is_prime_value(n: &FheUint16, encrypted_indices: &[FheUint16]) -> FheBool {
+    PRIME_FIBONACCI_INDICES
+        .iter()
+        .map(|i| index_matches(n, encrypted_indices, usize::from(*i)))
+        .reduce(|acc, is_match| acc | is_match)
+        .expect("PRIME_FIBONACCI_INDICES is not empty")
+}
+
+/// Perfect squares `n <= MAX_FIBONACCI_INDEX`, the indices kept by
+/// [`fibonacci_lookup_square_indices`].
+pub const SQUARE_INDICES: [u16; 5] = [0, 1, 4, 9, 16];
+
+/// `F(n)` when `n` is one of the [`SQUARE_INDICES`], an encrypted zero otherwise.
+///
+/// ORs the equality flags of `n` against the square entries of
+/// `encrypted_indices` into an `is_square` flag, then ANDs it with the lookup
+/// through a `select` against zero. `F(0) = 0`, so `n = 0` is indistinguishable
+/// from a filtered index. Requires the server key to be set on the calling
+/// thread.
+pub fn fibonacci_lookup_square_indices(
+    n: &FheUint16,
+    encrypted_indices: &[FheUint16],
+    encrypted_fibs: &[FheUint16],
+) -> FheUint16 {
+    let is_square = SQUARE_INDICES
+        .iter()
+        .map(|i| index_matches(n, encrypted_indices, usize::from(*i)))
+        .reduce(|acc, is_match| acc | is_match)
+        .expect("SQUARE_INDICES is not empty");
+    let fib = fibonacci_lookup_with_tables(n, encrypted_indices, encrypted_fibs);
+    ops::select(&is_square, &fib, &FheUint16::encrypt_trivial(0u16))
+}
+
+/// The match flags of the lookup packed into one integer: bit `i` is set when
+/// `n` equals `encrypted_indices[i]`.
+///
+/// For an index in the table exactly one bit is set, so the client recovers `n`
+/// as the position of that bit, e.g. `n = 5` decrypts to 32; past the table the
+/// mask is 0. Each flag is cast to `FheUint32`, shifted into position with a
+/// scalar shift and ORed into the mask. Requires the server key to be set on the
+/// calling thread.
+///
+/// # Panics
+///
+/// If `encrypted_indices` has more than 32 entries.
+pub fn match_flag_bitmask(n: &FheUint16, encrypted_indices: &[FheUint16]) -> FheUint32 {
+    assert!(encrypted_indices.len() <= 32, "one bit per index must fit in FheUint32");
+    (0..encrypted_indices.len())
+        .zip(0u32..)
+        .map(|(i, bit)| FheUint32::cast_from(index_matches(n, encrypted_indices, i)) << bit)
+        .reduce(|mask, flag| mask | flag)
+        .unwrap_or_else(|| FheUint32::encrypt_trivial(0u32))
+}
+
+/// [`fibonacci_lookup_with_tables`] over many encrypted inputs, sharing one pair
+/// of tables and running the queries in parallel with rayon.
+///
+/// The server key is thread-local in tfhe, so it is passed explicitly and scoped
+/// with [`with_server_key`] to each query on the rayon workers: no key is left
+/// behind on the pool once the batch returns.
+pub fn fibonacci_lookup_batch(
+    ns: &[FheUint16],
+    encrypted_indices: &[FheUint16],
+    encrypted_fibs: &[FheUint16],
+    server_key: &ServerKey,
+) -> Vec<FheUint16> {
+    ns.par_iter()
+        .map(|n| {
+            with_server_key(server_key.clone(), || fibonacci_lookup_with_tables(n, encrypted_indices, encrypted_fibs))
+        })
+        .collect()
+}
+
+/// Lookup reducing the table with a balanced tree instead of a select chain.
+///
+/// All `is_match` flags are computed in parallel, each `encrypted_fibs[i]` is
+/// multiplied by its flag (a `select` against zero), and the masked entries are
+/// summed pairwise by rayon's `reduce`. At most one flag is set, so the sum is
+/// `F(n)`, or `0` when `n` is out of range. The critical path is `log2(25)`
+/// additions instead of 24 dependent selects.
+///
+/// As in [`fibonacci_lookup_batch`], the server key is scoped to each step, on
+/// whichever thread runs it.
+pub fn fibonacci_lookup_tree(
+    n: &FheUint16,
+    encrypted_indices: &[FheUint16],
+    encrypted_fibs: &[FheUint16],
+    server_key: &ServerKey,
+) -> FheUint16 {
+    let scoped = |f: &dyn Fn() -> FheUint16| with_server_key(server_key.clone(), f);
+    let zero = scoped(&|| FheUint16::encrypt_trivial(0u16));
+    encrypted_fibs
+        .par_iter()
+        .enumerate()
+        .map(|(i, fib)| scoped(&|| ops::select(&index_matches(n, encrypted_indices, i), fib, &zero)))
+        .reduce_with(|a, b| scoped(&|| ops::add(&a, &b)))
+        .unwrap_or(zero)
+}
+
+/// Fast-doubling over the bits of an encrypted index.
+///
+/// Starting from `(F(0), F(1))`, each bit of `n` from the most significant one
+/// down doubles the pair with `F(2k) = F(k) * (2*F(k+1) - F(k))` and
+/// `F(2k+1) = F(k)^2 + F(k+1)^2`, then shifts it by one when the bit is set. Only
+/// the `ceil(log2(MAX_FIBONACCI_INDEX + 1))` low bits are visited, so this runs 5
+/// iterations instead of 24.
+///
+/// The bits of `n` are never decrypted: bit `k` is extracted as the encrypted
+/// flag `(n & (1 << k)) != 0` (a scalar bitand followed by a scalar comparison),
+/// and the "shift by one" step is a pair of `select`s on that flag. Intermediate
+/// products may exceed `u16::MAX` (the last doubling computes `F(25)`), but the
+/// arithmetic is modulo `2^16` throughout so every `F(n)` with
+/// `n <= MAX_FIBONACCI_INDEX` comes out exact.
+///
+/// The low bits alone would give `F(n mod 32)` for a larger `n`, so a final
+/// `select` on the scalar comparison `n <= MAX_FIBONACCI_INDEX` zeroes the
+/// result past the table, like the lookup's fall-through to `F(0)`.
+///
+/// Requires the server key to be set on the calling thread.
+pub fn fibonacci_fast_doubling(n: &FheUint16, pks: &PublicKey) -> FheUint16 {
+    let index_bits = u16::BITS - MAX_FIBONACCI_INDEX.leading_zeros();
+
+    // invariant: a = F(k), b = F(k+1) where k is the prefix of n processed so far.
+    let mut a = FheUint16::encrypt(0u16, pks);
+    let mut b = FheUint16::encrypt(1u16, pks);
+
+    for bit in (0..index_bits).rev() {
+        // F(2k) and F(2k+1)
+        let c = ops::mul(&a, &ops::sub(&ops::scalar_mul(&b, 2u16), &a));
+        let d = ops::add(&ops::mul(&a, &a), &ops::mul(&b, &b));
+
+        let bit_is_set = ops::ne(&(n & (1u16 << bit)), 0u16);
+        let c_plus_d = ops::add(&c, &d);
+        a = ops::select(&bit_is_set, &d, &c);
+        b = ops::select(&bit_is_set, &c_plus_d, &d);
+    }
+
+    zero_out_of_range(n, &a)
+}
+
+/// `value` if `n <= MAX_FIBONACCI_INDEX`, an encrypted zero otherwise: the
+/// result of the lookup past the table, for the strategies that only read the low
+/// bits of `n`.
+fn zero_out_of_range(n: &FheUint16, value: &FheUint16) -> FheUint16 {
+    let in_range = ops::le(n, MAX_FIBONACCI_INDEX);
+    ops::select(&in_range, value, &FheUint16::encrypt_trivial(0u16))
+}
+
+/// 2x2 matrix of encrypted values, row-major.
+pub type FheMat2 = [[FheUint16; 2]; 2];
+
+/// Homomorphic 2x2 matrix multiply `a * b`.
+///
+/// Requires the server key to be set on the calling thread.
+pub fn fhe_mat2_mul(a: &FheMat2, b: &FheMat2) -> FheMat2 {
+    let entry = |i: usize, j: usize| ops::add(&ops::mul(&a[i][0], &b[0][j]), &ops::mul(&a[i][1], &b[1][j]));
+    [[entry(0, 0), entry(0, 1)], [entry(1, 0), entry(1, 1)]]
+}
+
+/// Matrix exponentiation: raises `[[1,1],[1,0]]` to the encrypted power `n` by
+/// repeated squaring.
+///
+/// `M^n = [[F(n+1), F(n)], [F(n), F(n-1)]]`, so `F(n)` is read from the top-right
+/// entry (the top-left one holds `F(n+1)`). Each of the low bits of `n` is
+/// extracted homomorphically as in [`fibonacci_fast_doubling`]; the running
+/// product is multiplied by the current square unconditionally and the bit flag
+/// `select`s, entry by entry, whether the product is kept. Past
+/// [`MAX_FIBONACCI_INDEX`] the result is zeroed, as in [`fibonacci_fast_doubling`].
+///
+/// Requires the server key to be set on the calling thread.
+pub fn fibonacci_matrix_pow(n: &FheUint16, pks: &PublicKey) -> FheUint16 {
+    let index_bits = u16::BITS - MAX_FIBONACCI_INDEX.leading_zeros();
+    let zero = FheUint16::encrypt(0u16, pks);
+    let one = FheUint16::encrypt(1u16, pks);
+
+    let mut result: FheMat2 = [[one.clone(), zero.clone()], [zero.clone(), one.clone()]];
+    let mut base: FheMat2 = [[one.clone(), one.clone()], [one, zero]];
+
+    for bit in 0..index_bits {
+        let bit_is_set = ops::ne(&(n & (1u16 << bit)), 0u16);
+        let product = fhe_mat2_mul(&result, &base);
+        for (row, product_row) in result.iter_mut().zip(&product) {
+            for (entry, product_entry) in row.iter_mut().zip(product_row) {
+                *entry = ops::select(&bit_is_set, product_entry, entry);
+            }
+        }
+        // The square is not needed after the most significant bit.
+        if bit + 1 < index_bits {
+            base = fhe_mat2_mul(&base, &base);
+        }
+    }
+
+    let [[_, f_n], _] = result;
+    zero_out_of_range(n, &f_n)
+}
+
+/// A strategy computing `F(n)` for an encrypted `n`: arguments are `n`, the
+/// public key and the tables of [`build_encrypted_indices`] and
+/// [`build_encrypted_fibs`], which strategies other than the lookup ignore.
+///
+/// Requires the server key to be set on the calling thread.
+pub type StrategyFn = fn(&FheUint16, &PublicKey, &[FheUint16], &[FheUint16]) -> FheUint16;
+
+/// Every strategy computing `F(n)` for `n <= MAX_FIBONACCI_INDEX`, by name; all
+/// of them give `F(0) = 0` past it.
+///
+/// Sweeps over all strategies, such as [`verify::verify_strategies`] and the
+/// `verify` binary, iterate over this list: a strategy added here is checked
+/// against the others without further changes.
+pub const STRATEGIES: &[(&str, StrategyFn)] = &[
+    ("additions", |n, pks, _, _| fibonacci_additions(n, pks)),
+    ("lookup", |n, _, indices, fibs| fibonacci_lookup_with_tables(n, indices, fibs)),
+    ("fast_doubling", |n, pks, _, _| fibonacci_fast_doubling(n, pks)),
+    ("matrix", |n, pks, _, _| fibonacci_matrix_pow(n, pks)),
+];
+
+/// Which of the additions, lookup and matrix strategies of [`STRATEGIES`] to
+/// run, as selected by `--strategy` and estimated by [`estimate::estimate_ops`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StrategyChoice {
+    Additions,
+    Lookup,
+    Matrix,
+    /// Additions and lookup.
+    Both,
+    /// Additions, lookup and matrix power.
+    #[default]
+    All,
+}
+
+impl StrategyChoice {
+    pub fn additions(self) -> bool {
+        matches!(self, Self::Additions | Self::Both | Self::All)
+    }
+
+    pub fn lookup(self) -> bool {
+        matches!(self, Self::Lookup | Self::Both | Self::All)
+    }
+
+    pub fn matrix(self) -> bool {
+        matches!(self, Self::Matrix | Self::All)
+    }
+}
+
+/// Number of homomorphic operations of each kind, as estimated by
+/// [`estimate::estimate_ops`] and recorded by `stats::record` with the `stats`
+/// feature.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct OpCounts {
+    /// Equality and ordering comparisons.
+    pub eq: u64,
+    pub select: u64,
+    /// Additions and subtractions.
+    pub add: u64,
+    /// Multiplications and divisions.
+    pub mul: u64,
+}
+
+impl std::ops::Add for OpCounts {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            eq: self.eq + other.eq,
+            select: self.select + other.select,
+            add: self.add + other.add,
+            mul: self.mul + other.mul,
+        }
+    }
+}
+
+impl std::fmt::Display for OpCounts {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} eq, {} select, {} add, {} mul", self.eq, self.select, self.add, self.mul)
+    }
+}
+
+/// Plaintext reference implementation used for verification.
+pub fn fibonacci_plaintext(n: u16) -> u16 {
+    let mut a: u16 = 0;
+    let mut b: u16 = 1;
+    for _ in 0..n {
+        // b runs one term ahead and wraps once n = MAX_FIBONACCI_INDEX.
+        let tmp = a.wrapping_add(b);
+        a = b;
+        b = tmp;
+    }
+    a
+}
+
+/// Build a plaintext Lucas table up to MAX_LUCAS_INDEX.
+pub fn build_lucas_table_plain() -> Vec<u16> {
+    sequence::build_table_plain::<Lucas>()
+}
+
+/// Build encrypted Lucas table from plaintext with parallelization.
+///
+/// Entry `i` is an encryption of `L(i)`, aligned with [`build_encrypted_indices`].
+pub fn build_encrypted_lucas(pks: &PublicKey) -> Vec<FheUint16> {
+    build_encrypted_table::<Lucas>(pks)
+}
+
+/// Lookup of `L(n)` over an encrypted table, the Lucas counterpart of
+/// [`fibonacci_lookup_with_tables`].
+///
+/// The Fibonacci index table from [`build_encrypted_indices`] can be reused; only
+/// its first `MAX_LUCAS_INDEX + 1` entries are read. Requires the server key to be
+/// set on the calling thread.
+pub fn lucas_lookup_with_tables(
+    n: &FheUint16,
+    encrypted_indices: &[FheUint16],
+    encrypted_lucas: &[FheUint16],
+) -> FheUint16 {
+    lookup_with_tables::<Lucas>(n, encrypted_indices, encrypted_lucas)
+}
+
+/// Plaintext Lucas reference implementation used for verification.
+pub fn lucas_plaintext(n: u16) -> u16 {
+    let mut a: u16 = 2;
+    let mut b: u16 = 1;
+    for _ in 0..n {
+        // b runs one term ahead and wraps once n = MAX_LUCAS_INDEX.
+        let tmp = a.wrapping_add(b);
+        a = b;
+        b = tmp;
+    }
+    a
+}
+
+/// 32-bit variant of [`fibonacci_additions`], covering indices up to
+/// [`MAX_FIBONACCI_INDEX_U32`].
+///
+/// Requires the server key to be set on the calling thread.
+pub fn fibonacci_additions_u32(n: &FheUint32, pks: &PublicKey) -> FheUint32 {
+    let encrypted_indices = build_encrypted_indices_u32(pks);
+    let Ok(result) = additions_loop(n, &encrypted_indices, |_| Ok::<_, Infallible>(()));
+    result
+}
+
+/// Build a plaintext 32-bit Fibonacci table up to MAX_FIBONACCI_INDEX_U32.
+pub fn build_fibonacci_table_plain_u32() -> Vec<u32> {
+    fibonacci_table(MAX_FIBONACCI_INDEX_U32 as usize + 1, Overflow::Checked)
+}
+
+/// Build encrypted 32-bit indices with parallelization.
+pub fn build_encrypted_indices_u32(pks: &PublicKey) -> Vec<FheUint32> {
+    (0..=MAX_FIBONACCI_INDEX_U32)
+        .into_par_iter()
+        .map(|i| FheUint32::encrypt(i, pks))
+        .collect()
+}
+
+/// Build encrypted 32-bit Fibonacci table from plaintext with parallelization.
+pub fn build_encrypted_fibs_u32(pks: &PublicKey) -> Vec<FheUint32> {
+    let fibs_plain = build_fibonacci_table_plain_u32();
+    fibs_plain
+        .par_iter()
+        .copied()
+        .map(|v| FheUint32::encrypt(v, pks))
+        .collect()
+}
+
+/// 32-bit variant of [`fibonacci_lookup_with_tables`], using the tables from
+/// [`build_encrypted_indices_u32`] and [`build_encrypted_fibs_u32`].
+///
+/// Requires the server key to be set on the calling thread.
+pub fn fibonacci_lookup_with_tables_u32(
+    n: &FheUint32,
+    encrypted_indices: &[FheUint32],
+    encrypted_fibs: &[FheUint32],
+) -> FheUint32 {
+    lookup_up_to(n, encrypted_indices, encrypted_fibs, MAX_FIBONACCI_INDEX_U32 as usize)
+}
+
+/// Build a plaintext 8-bit Fibonacci table up to MAX_FIBONACCI_INDEX_U8.
+pub fn build_fibonacci_table_plain_u8() -> Vec<u8> {
+    fibonacci_table(usize::from(MAX_FIBONACCI_INDEX_U8) + 1, Overflow::Checked)
+}
+
+/// Plaintext 32-bit reference implementation used for verification.
+pub fn fibonacci_plaintext_u32(n: u32) -> u32 {
+    let mut a: u32 = 0;
+    let mut b: u32 = 1;
+    for _ in 0..n {
+        // b runs one term ahead and wraps once n = MAX_FIBONACCI_INDEX_U32.
+        let tmp = a.wrapping_add(b);
+        a = b;
+        b = tmp;
+    }
+    a
+}
+
+/// Build a plaintext 16-bit Fibonacci table up to MAX_SATURATING_INDEX, clamping
+/// every entry that overflows `u16` to `u16::MAX`.
+pub fn build_fibonacci_table_saturating() -> Vec<u16> {
+    fibonacci_table(usize::from(MAX_SATURATING_INDEX) + 1, Overflow::Saturating)
+}
+
+/// Build encrypted indices `0..=MAX_SATURATING_INDEX` with parallelization.
+pub fn build_encrypted_indices_saturating(pks: &PublicKey) -> Vec<FheUint16> {
+    (0..=MAX_SATURATING_INDEX)
+        .into_par_iter()
+        .map(|i| FheUint16::encrypt(i, pks))
+        .collect()
+}
+
+/// Build the encrypted saturating Fibonacci table with parallelization.
+pub fn build_encrypted_fibs_saturating(pks: &PublicKey) -> Vec<FheUint16> {
+    build_fibonacci_table_saturating()
+        .par_iter()
+        .copied()
+        .map(|v| FheUint16::encrypt(v, pks))
+        .collect()
+}
+
+/// Lookup over the saturating tables: `F(n)` for `n <= MAX_FIBONACCI_INDEX`, an
+/// encrypted `u16::MAX` overflow sentinel up to [`MAX_SATURATING_INDEX`].
+///
+/// Indices past `MAX_SATURATING_INDEX` still fall back to `F(0)`.
+/// Requires the server key to be set on the calling thread.
+pub fn fibonacci_lookup_saturating(
+    n: &FheUint16,
+    encrypted_indices: &[FheUint16],
+    encrypted_fibs: &[FheUint16],
+) -> FheUint16 {
+    let mut result = encrypted_fibs[0].clone();
+    for (i, fib) in encrypted_fibs[..=usize::from(MAX_SATURATING_INDEX)].iter().enumerate().skip(1) {
+        let is_match = index_matches(n, encrypted_indices, i);
+        result = ops::select(&is_match, fib, &result);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use proptest::test_runner::RngSeed;
+    use crate::test_support::TestHarness;
+    use tfhe::ClientKey;
+
+    #[test]
+    fn test_diff_fibonacci() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            // Test for a range of small n
+            for n in 0u16..=10 {
+                let encrypted = harness.encrypt(n);
+                let dec_add: u16 = harness.decrypt(&fibonacci_additions(&encrypted, harness.pks()));
+                let enc_lt = fibonacci_lookup_with_tables(&encrypted, harness.indices(), harness.fibs());
+                let dec_lt: u16 = harness.decrypt(&enc_lt);
+
+                let expected = fibonacci_plaintext(n);
+
+                assert_eq!(
+                    dec_add, expected,
+                    "Additions mismatch for n = {}: encrypted = {}, plaintext = {}",
+                    n, dec_add, expected
+                );
+                assert_eq!(
+                    dec_lt, expected,
+                    "Lookup mismatch for n = {}: encrypted = {}, plaintext = {}",
+                    n, dec_lt, expected
+                );
+            }
+        });
+    }
+
+    #[test]
+    fn test_strategies_out_of_range() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            // 33 has the low bits of 1: fast doubling and matrix power must not return F(1).
+            for n in [MAX_FIBONACCI_INDEX + 1, 33, u16::MAX] {
+                let encrypted = harness.encrypt(n);
+                for (name, compute) in STRATEGIES {
+                    let dec: u16 = harness.decrypt(&compute(&encrypted, harness.pks(), harness.indices(), harness.fibs()));
+                    assert_eq!(dec, 0, "{} mismatch for out-of-range n = {}", name, n);
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn test_fibonacci_u32() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            let n = 40u32;
+            let encrypted = FheUint32::encrypt(n, harness.client_key());
+            let enc_add = fibonacci_additions_u32(&encrypted, harness.pks());
+            let encrypted_indices = build_encrypted_indices_u32(harness.pks());
+            let encrypted_fibs = build_encrypted_fibs_u32(harness.pks());
+            let enc_lt = fibonacci_lookup_with_tables_u32(&encrypted, &encrypted_indices, &encrypted_fibs);
+            let dec_add: u32 = harness.decrypt(&enc_add);
+            let dec_lt: u32 = harness.decrypt(&enc_lt);
+
+            assert_eq!(fibonacci_plaintext_u32(n), 102334155);
+            assert_eq!(dec_add, 102334155, "Additions mismatch for n = {}", n);
+            assert_eq!(dec_lt, 102334155, "Lookup mismatch for n = {}", n);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "index 47 is past the end of a table of 25 values")]
+    fn test_fibonacci_lookup_u32_short_table() {
+        // The check runs before any homomorphic operation: no server key needed.
+        let harness = TestHarness::new();
+        let short: Vec<FheUint32> = (0..25u32).map(|i| FheUint32::encrypt(i, harness.client_key())).collect();
+        fibonacci_lookup_with_tables_u32(&short[0], &short, &short);
+    }
+
+    #[test]
+    fn test_fibonacci_lookup_u8() {
+        assert_eq!(sequence::max_index_u8::<Fibonacci>(), u16::from(MAX_FIBONACCI_INDEX_U8));
+
+        let harness = TestHarness::new();
+        let encrypted_fibs = sequence::build_encrypted_table_u8::<Fibonacci>(harness.pks());
+        harness.run(|| {
+            for n in 0..=u16::from(MAX_FIBONACCI_INDEX_U8) {
+                let encrypted = harness.encrypt(n);
+                let enc_lt = sequence::lookup_with_tables_u8::<Fibonacci>(&encrypted, harness.indices(), &encrypted_fibs);
+                let dec_lt: u8 = harness.decrypt(&enc_lt);
+                assert_eq!(u16::from(dec_lt), fibonacci_plaintext(n), "8-bit lookup mismatch for n = {}", n);
+            }
+        });
+    }
+
+    #[test]
+    fn test_fibonacci_fast_doubling() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            for n in 0u16..=MAX_FIBONACCI_INDEX {
+                let encrypted = harness.encrypt(n);
+                let enc_fd = fibonacci_fast_doubling(&encrypted, harness.pks());
+                let dec_fd: u16 = harness.decrypt(&enc_fd);
+
+                let expected = fibonacci_plaintext(n);
+
+                assert_eq!(
+                    dec_fd, expected,
+                    "Fast doubling mismatch for n = {}: encrypted = {}, plaintext = {}",
+                    n, dec_fd, expected
+                );
+            }
+        });
+    }
+
+    #[test]
+    fn test_fibonacci_matrix_pow() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            for n in 0u16..=MAX_FIBONACCI_INDEX {
+                let encrypted = harness.encrypt(n);
+                let enc_mp = fibonacci_matrix_pow(&encrypted, harness.pks());
+                let dec_mp: u16 = harness.decrypt(&enc_mp);
+
+                let expected = fibonacci_plaintext(n);
+
+                assert_eq!(
+                    dec_mp, expected,
+                    "Matrix power mismatch for n = {}: encrypted = {}, plaintext = {}",
+                    n, dec_mp, expected
+                );
+            }
+        });
+    }
+
+    #[test]
+    fn test_fibonacci_lookup_batch() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            let ns: Vec<FheUint16> = [3u16, 5, 8, 13]
+                .iter()
+                .map(|n| harness.encrypt(*n))
+                .collect();
+
+            let batch = fibonacci_lookup_batch(&ns, harness.indices(), harness.fibs(), harness.server_key());
+            assert_eq!(batch.len(), ns.len());
+            for (n, enc_batch) in ns.iter().zip(&batch) {
+                let enc_single = fibonacci_lookup_with_tables(n, harness.indices(), harness.fibs());
+                let dec_batch: u16 = harness.decrypt(enc_batch);
+                let dec_single: u16 = harness.decrypt(&enc_single);
+                assert_eq!(dec_batch, dec_single);
+            }
+        });
+    }
+
+    #[test]
+    fn test_encrypt_inputs_batch() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            let ns = encrypt_inputs(&[3, 7, 11], harness.client_key());
+            let batch = fibonacci_lookup_batch(&ns, harness.indices(), harness.fibs(), harness.server_key());
+
+            let decrypted: Vec<u16> = batch.iter().map(|ct| harness.decrypt(ct)).collect();
+            assert_eq!(decrypted, [2, 13, 89]);
+        });
+    }
+
+    #[test]
+    fn test_fibonacci_lookup_tree() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            for n in 0u16..=MAX_FIBONACCI_INDEX {
+                let encrypted = harness.encrypt(n);
+                let enc_tree =
+                    fibonacci_lookup_tree(&encrypted, harness.indices(), harness.fibs(), harness.server_key());
+                let dec_tree: u16 = harness.decrypt(&enc_tree);
+
+                let expected = fibonacci_plaintext(n);
+
+                assert_eq!(
+                    dec_tree, expected,
+                    "Tree lookup mismatch for n = {}: encrypted = {}, plaintext = {}",
+                    n, dec_tree, expected
+                );
+            }
+        });
+    }
+
+    #[test]
+    fn test_lucas_lookup() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            assert_eq!(lucas_plaintext(MAX_LUCAS_INDEX), 64079);
+            assert_eq!(build_lucas_table_plain()[..5], [2, 1, 3, 4, 7]);
+
+            let encrypted_lucas = build_encrypted_lucas(harness.pks());
+            for n in [0u16, 1, 2, 10, MAX_LUCAS_INDEX] {
+                let encrypted = harness.encrypt(n);
+                let enc_lt = lucas_lookup_with_tables(&encrypted, harness.indices(), &encrypted_lucas);
+                let dec_lt: u16 = harness.decrypt(&enc_lt);
+
+                let expected = lucas_plaintext(n);
+
+                assert_eq!(
+                    dec_lt, expected,
+                    "Lucas lookup mismatch for n = {}: encrypted = {}, plaintext = {}",
+                    n, dec_lt, expected
+                );
+            }
+        });
+    }
+
+    #[test]
+    fn test_fibonacci_lookup_checked() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            let valid = harness.encrypt(24u16);
+            let (enc_result, enc_flag) = fibonacci_lookup_checked(&valid, harness.indices(), harness.fibs());
+            let result: u16 = harness.decrypt(&enc_result);
+            assert_eq!(result, fibonacci_plaintext(24));
+            assert!(!harness.decrypt(&enc_flag), "24 must not be flagged");
+
+            let invalid = harness.encrypt(25u16);
+            let (_, enc_flag) = fibonacci_lookup_checked(&invalid, harness.indices(), harness.fibs());
+            assert!(harness.decrypt(&enc_flag), "25 must be flagged as out of range");
+        });
+    }
+
+    #[test]
+    fn test_fibonacci_composed() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            // F(5) = 5 -> F(5) = 5, F(6) = 8 -> F(8) = 21
+            for (n, expected) in [(5u16, 5u16), (6, 21)] {
+                let encrypted = harness.encrypt(n);
+                let dec: u16 = harness.decrypt(&fibonacci_composed(&encrypted, harness.indices(), harness.fibs()));
+                assert_eq!(dec, expected, "F(F({}))", n);
+            }
+
+            let valid = harness.encrypt(MAX_COMPOSED_INDEX);
+            let (enc_result, enc_flag) = fibonacci_composed_checked(&valid, harness.indices(), harness.fibs());
+            let result: u16 = harness.decrypt(&enc_result);
+            assert_eq!(result, fibonacci_plaintext(21));
+            assert!(!harness.decrypt(&enc_flag), "8 must not be flagged");
+
+            // F(9) = 34 is past the table.
+            let invalid = harness.encrypt(MAX_COMPOSED_INDEX + 1);
+            let (_, enc_flag) = fibonacci_composed_checked(&invalid, harness.indices(), harness.fibs());
+            assert!(harness.decrypt(&enc_flag), "9 must be flagged as out of range");
+
+            // F(25) looks up as F(0) = 0, a valid index: only the check of n catches it.
+            let past_table = harness.encrypt(MAX_FIBONACCI_INDEX + 1);
+            let (enc_result, enc_flag) = fibonacci_composed_checked(&past_table, harness.indices(), harness.fibs());
+            let result: u16 = harness.decrypt(&enc_result);
+            assert_eq!(result, 0);
+            assert!(harness.decrypt(&enc_flag), "25 must be flagged as out of range");
+        });
+    }
+
+    #[test]
+    fn test_fibonacci_value_gt() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            // F(1) = F(2) = 1, F(3) = 2
+            for (n, m, expected) in [(1u16, 2u16, false), (3, 2, true)] {
+                let enc_n = harness.encrypt(n);
+                let enc_m = harness.encrypt(m);
+                let enc_gt = fibonacci_value_gt(&enc_n, &enc_m, harness.indices(), harness.fibs());
+                assert_eq!(harness.decrypt(&enc_gt), expected, "F({}) > F({})", n, m);
+            }
+        });
+    }
+
+    #[test]
+    fn test_fibonacci_lookup_saturating() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            let fibs_plain = build_fibonacci_table_saturating();
+            assert_eq!(fibs_plain[..=usize::from(MAX_FIBONACCI_INDEX)], build_fibonacci_table_plain()[..]);
+
+            let encrypted_indices = build_encrypted_indices_saturating(harness.pks());
+            let encrypted_fibs = build_encrypted_fibs_saturating(harness.pks());
+            for n in [0u16, 10, 24, 25, 30] {
+                let expected = if n <= MAX_FIBONACCI_INDEX { fibonacci_plaintext(n) } else { u16::MAX };
+                let encrypted = harness.encrypt(n);
+                let enc_sat = fibonacci_lookup_saturating(&encrypted, &encrypted_indices, &encrypted_fibs);
+                let dec_sat: u16 = harness.decrypt(&enc_sat);
+
+                assert_eq!(
+                    dec_sat, expected,
+                    "Saturating lookup mismatch for n = {}: encrypted = {}, expected = {}",
+                    n, dec_sat, expected
+                );
+            }
+        });
+    }
+
+    #[test]
+    fn test_trivial_tables() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            let trivial_indices = build_trivial_indices();
+            let trivial_fibs = build_trivial_fibs();
+            for n in 0u16..=10 {
+                let encrypted = harness.encrypt(n);
+                let enc_lt = fibonacci_lookup_with_tables(&encrypted, harness.indices(), harness.fibs());
+                let enc_triv = fibonacci_lookup_with_tables(&encrypted, &trivial_indices, &trivial_fibs);
+                let dec_lt: u16 = harness.decrypt(&enc_lt);
+                let dec_triv: u16 = harness.decrypt(&enc_triv);
+
+                assert_eq!(
+                    dec_triv, dec_lt,
+                    "Trivial lookup mismatch for n = {}: trivial = {}, encrypted = {}",
+                    n, dec_triv, dec_lt
+                );
+            }
+        });
+    }
+
+    /// Fail if running `f` on the encryptions of `inputs` takes more than `factor`
+    /// times longer for one input than for another, e.g. because of an
+    /// accidental early exit on the plaintext value.
+    fn assert_data_independent_timing(
+        client_key: &ClientKey,
+        inputs: &[u16],
+        factor: f64,
+        f: impl Fn(&FheUint16) -> FheUint16,
+    ) {
+        let timings: Vec<f64> = inputs
+            .iter()
+            .map(|n| {
+                let encrypted = FheUint16::encrypt(*n, client_key);
+                let start = std::time::Instant::now();
+                f(&encrypted);
+                start.elapsed().as_secs_f64()
+            })
+            .collect();
+        let fastest = timings.iter().copied().fold(f64::INFINITY, f64::min);
+        let slowest = timings.iter().copied().fold(0.0, f64::max);
+        assert!(
+            slowest <= fastest * factor,
+            "timing depends on the input: {:?} s for inputs {:?}",
+            timings,
+            inputs
+        );
+    }
+
+    // Wall-clock timing is only meaningful on an otherwise idle machine; run with
+    // `cargo test --release -- --ignored --test-threads=1`.
+    #[test]
+    #[ignore = "timing-sensitive, run alone with --ignored --test-threads=1"]
+    fn test_lookup_data_independent_timing() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            // Generous factor: this guards against early exits, not scheduling noise.
+            let schedule = [0, MAX_FIBONACCI_INDEX, 0, MAX_FIBONACCI_INDEX];
+            assert_data_independent_timing(harness.client_key(), &schedule, 2.0, |n| {
+                fibonacci_lookup_with_tables(n, harness.indices(), harness.fibs())
+            });
+        });
+    }
+
+    #[test]
+    fn test_count_matches() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            for (n, expected) in [(0u16, 1), (MAX_FIBONACCI_INDEX, 1), (MAX_FIBONACCI_INDEX + 1, 0)] {
+                let encrypted = harness.encrypt(n);
+                assert_eq!(count_matches(&encrypted, harness.indices(), harness.client_key()), expected, "n = {}", n);
+            }
+
+            // A malformed table with a duplicated index shows up as two matches.
+            let mut malformed_indices = harness.indices().to_vec();
+            malformed_indices[2] = FheUint16::encrypt(1u16, harness.pks());
+            let encrypted = harness.encrypt(1u16);
+            assert_eq!(count_matches(&encrypted, &malformed_indices, harness.client_key()), 2);
+        });
+    }
+
+    #[test]
+    fn test_fibonacci_prefix_sum() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            for n in 0u16..=MAX_PREFIX_SUM_INDEX {
+                let encrypted = harness.encrypt(n);
+                let enc_sum = fibonacci_prefix_sum(&encrypted, harness.indices(), harness.fibs());
+                let dec_sum: u16 = harness.decrypt(&enc_sum);
+
+                let expected: u16 = (0..=n).map(fibonacci_plaintext).sum();
+                assert_eq!(
+                    dec_sum, expected,
+                    "Prefix sum mismatch for n = {}: encrypted = {}, plaintext = {}",
+                    n, dec_sum, expected
+                );
+            }
+        });
+    }
+
+    #[test]
+    fn test_fibonacci_prefix_sum_checked() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            // Past the range, the lookup reads index n + 2 mod 2^16: u16::MAX - 2 matches
+            // no index, u16::MAX - 1 wraps to index 0 and u16::MAX to index 1.
+            let cases = [
+                (MAX_PREFIX_SUM_INDEX, 46367u16, false),
+                (23, u16::MAX, true),
+                (u16::MAX - 2, u16::MAX, true),
+                (u16::MAX - 1, u16::MAX, true),
+                (u16::MAX, 0, true),
+            ];
+            for (n, expected_sum, expected_flag) in cases {
+                let encrypted = harness.encrypt(n);
+                let (enc_sum, enc_flag) = fibonacci_prefix_sum_checked(&encrypted, harness.indices(), harness.fibs());
+                let dec_sum: u16 = harness.decrypt(&enc_sum);
+                assert_eq!(dec_sum, expected_sum, "Prefix sum mismatch for n = {}", n);
+                assert_eq!(harness.decrypt(&enc_flag), expected_flag, "Out-of-range flag mismatch for n = {}", n);
+            }
+        });
+    }
+
+    #[test]
+    fn test_fibonacci_additions_trace() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            let encrypted = harness.encrypt(8u16);
+            let trace: Vec<u16> = fibonacci_additions_trace(&encrypted, harness.pks())
+                .iter()
+                .map(|ct| harness.decrypt(ct))
+                .collect();
+
+            let (result, terms) = trace.split_last().unwrap();
+            assert_eq!(terms, build_fibonacci_table_plain(), "trace does not match the plaintext prefix");
+            assert_eq!(*result, fibonacci_plaintext(8));
+        });
+    }
+
+    #[test]
+    fn test_fibonacci_additions_cancellable() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            let encrypted = harness.encrypt(8u16);
+            let cancelled = AtomicBool::new(true);
+
+            let result = fibonacci_additions_cancellable(&encrypted, harness.pks(), &cancelled);
+            assert_eq!(result.err(), Some(Cancelled));
+        });
+    }
+
+    #[test]
+    fn test_fibonacci_lookup_array() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            let array_indices = build_encrypted_indices_array::<FIBONACCI_TABLE_LEN>(harness.pks());
+            let array_fibs = build_encrypted_fibs_array::<FIBONACCI_TABLE_LEN>(harness.pks());
+            for n in [0u16, 7, MAX_FIBONACCI_INDEX] {
+                let encrypted = harness.encrypt(n);
+                let enc_vec = fibonacci_lookup_with_tables(&encrypted, harness.indices(), harness.fibs());
+                let enc_array = fibonacci_lookup_array(&encrypted, &array_indices, &array_fibs);
+                let dec_vec: u16 = harness.decrypt(&enc_vec);
+                let dec_array: u16 = harness.decrypt(&enc_array);
+
+                assert_eq!(
+                    dec_array, dec_vec,
+                    "Array lookup mismatch for n = {}: array = {}, vec = {}",
+                    n, dec_array, dec_vec
+                );
+            }
+        });
+    }
+
+    #[test]
+    fn test_fibonacci_index_of() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            for (v, expected) in [(21u16, 8u16), (100, 0), (fibonacci_plaintext(MAX_FIBONACCI_INDEX), MAX_FIBONACCI_INDEX)] {
+                let encrypted = harness.encrypt(v);
+                let enc_index = fibonacci_index_of(&encrypted, harness.indices(), harness.fibs());
+                let dec_index: u16 = harness.decrypt(&enc_index);
+
+                assert_eq!(dec_index, expected, "Index mismatch for v = {}: encrypted = {}", v, dec_index);
+            }
+        });
+    }
+
+    #[test]
+    fn test_is_fibonacci_number() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            // 1 is both F(1) and F(2).
+            for (v, expected) in [(34u16, true), (35, false), (1, true), (0, true), (u16::MAX, false)] {
+                let encrypted = harness.encrypt(v);
+                let dec_member = harness.decrypt(&is_fibonacci_number(&encrypted, harness.fibs()));
+
+                assert_eq!(dec_member, expected, "Membership mismatch for v = {}: encrypted = {}", v, dec_member);
+            }
+        });
+    }
+
+    #[test]
+    fn test_try_fibonacci_lookup_with_tables() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            let encrypted = harness.encrypt(10u16);
+            let enc_fib = try_fibonacci_lookup_with_tables(&encrypted, harness.indices(), harness.fibs()).unwrap();
+            let dec_fib: u16 = harness.decrypt(&enc_fib);
+            assert_eq!(dec_fib, 55);
+
+            let short_fibs = &harness.fibs()[..20];
+            assert_eq!(
+                try_fibonacci_lookup_with_tables(&encrypted, harness.indices(), short_fibs).err(),
+                Some(TableMismatch { indices: FIBONACCI_TABLE_LEN, fibs: 20 })
+            );
+            assert_eq!(
+                check_tables(&harness.indices()[..20], short_fibs),
+                Err(TableMismatch { indices: 20, fibs: 20 })
+            );
+        });
+    }
+
+    #[test]
+    fn test_fibonacci_masked_sum() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            let mut mask = [false; MAX_FIBONACCI_INDEX as usize + 1];
+            for i in [5, 7, 9] {
+                mask[i] = true;
+            }
+            // F(5) + F(7) + F(9) = 5 + 13 + 34
+            let dec_sum: u16 = harness.decrypt(&fibonacci_masked_sum(&mask, harness.fibs()));
+            assert_eq!(dec_sum, 52);
+
+            let dec_empty: u16 = harness.decrypt(&fibonacci_masked_sum(&[], harness.fibs()));
+            assert_eq!(dec_empty, 0);
+        });
+    }
+
+    #[test]
+    fn test_count_fibs_below() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            // F(0..=8) = 0, 1, 1, 2, 3, 5, 8, 13, 21 are below 22, with 1 counted twice.
+            for (v, expected) in [(22u16, 9u16), (21, 8), (0, 0)] {
+                let encrypted = harness.encrypt(v);
+                let dec_count: u16 = harness.decrypt(&count_fibs_below(&encrypted, harness.fibs()));
+
+                assert_eq!(dec_count, expected, "Count mismatch for v = {}: encrypted = {}", v, dec_count);
+            }
+        });
+    }
+
+    #[test]
+    fn test_fibonacci_mod() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            for (n, expected) in [(20u16, 65u16), (10, 55)] {
+                let encrypted = harness.encrypt(n);
+                let enc_mod = fibonacci_mod(&encrypted, harness.indices(), harness.fibs(), 100);
+                let dec_mod: u16 = harness.decrypt(&enc_mod);
+
+                assert_eq!(dec_mod, expected, "F({}) mod 100 mismatch: encrypted = {}", n, dec_mod);
+            }
+        });
+    }
+
+    #[test]
+    fn test_fibonacci_digit_sum() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            for n in [0u16, 7, 10, 17, MAX_FIBONACCI_INDEX] {
+                let encrypted = harness.encrypt(n);
+                let enc_sum = fibonacci_digit_sum(&encrypted, harness.indices(), harness.fibs());
+                let dec_sum: u16 = harness.decrypt(&enc_sum);
+
+                let expected: u16 = fibonacci_plaintext(n).to_string().bytes().map(|digit| u16::from(digit - b'0')).sum();
+                assert_eq!(dec_sum, expected, "Digit sum mismatch for n = {}: encrypted = {}", n, dec_sum);
+            }
+        });
+    }
+
+    #[test]
+    fn test_fibonacci_range() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            let fibs = build_fibonacci_table_plain();
+            for (lo, hi) in [(5u16, 8u16), (8, 5)] {
+                let enc_lo = harness.encrypt(lo);
+                let enc_hi = harness.encrypt(hi);
+                let enc_range = fibonacci_range(&enc_lo, &enc_hi, harness.indices(), harness.fibs());
+                let dec_range: Vec<u16> = enc_range.iter().map(|ct| harness.decrypt(ct)).collect();
+
+                let expected: Vec<u16> = (0..=MAX_FIBONACCI_INDEX)
+                    .map(|i| if lo <= i && i <= hi { fibs[usize::from(i)] } else { 0 })
+                    .collect();
+                assert_eq!(dec_range, expected, "Range mismatch for lo = {}, hi = {}", lo, hi);
+            }
+        });
+    }
+
+    #[test]
+    fn test_fibonacci_lookup_up_to() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            let max_index = 5;
+            let encrypted_indices = build_encrypted_indices_up_to(max_index, harness.pks());
+            let encrypted_fibs = build_encrypted_fibs_up_to(max_index, harness.pks());
+            assert_eq!(encrypted_indices.len(), 6);
+            assert_eq!(encrypted_fibs.len(), 6);
+            for n in 0..=max_index + 1 {
+                let encrypted = harness.encrypt(n);
+                let enc_lt = fibonacci_lookup_up_to(&encrypted, &encrypted_indices, &encrypted_fibs, max_index);
+                let dec_lt: u16 = harness.decrypt(&enc_lt);
+
+                // Past the table, the lookup falls through to F(0).
+                let expected = if n <= max_index { fibonacci_plaintext(n) } else { 0 };
+                assert_eq!(dec_lt, expected, "Lookup mismatch for n = {} with max_index = {}", n, max_index);
+            }
+        });
+    }
+
+    #[test]
+    fn test_fibonacci_lookup_clamped() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            for (n, expected) in [(100u16, 46368u16), (MAX_FIBONACCI_INDEX, 46368), (9, 34)] {
+                let encrypted = harness.encrypt(n);
+                let enc_lt = fibonacci_lookup_clamped(&encrypted, harness.indices(), harness.fibs());
+                let dec_lt: u16 = harness.decrypt(&enc_lt);
+
+                assert_eq!(dec_lt, expected, "Clamped lookup mismatch for n = {}: encrypted = {}", n, dec_lt);
+            }
+        });
+    }
+
+    #[test]
+    fn test_fibonacci_consecutive_pair() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            for (n, expected) in [(10u16, (55u16, 89u16)), (MAX_FIBONACCI_INDEX, (46368, u16::MAX))] {
+                let encrypted = harness.encrypt(n);
+                let (enc_fib, enc_next) = fibonacci_consecutive_pair(&encrypted, harness.indices(), harness.fibs());
+                let decrypted: (u16, u16) = (harness.decrypt(&enc_fib), harness.decrypt(&enc_next));
+
+                assert_eq!(decrypted, expected, "Pair mismatch for n = {}", n);
+            }
+        });
+    }
+
+    #[test]
+    fn test_fibonacci_is_even() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            for n in 0..=MAX_FIBONACCI_INDEX {
+                let encrypted = harness.encrypt(n);
+                let enc_even = fibonacci_is_even(&encrypted, harness.indices(), harness.fibs());
+                let dec_even = harness.decrypt(&enc_even);
+
+                let expected = fibonacci_plaintext(n).is_multiple_of(2);
+                assert_eq!(dec_even, expected, "Parity mismatch for n = {}: encrypted = {}", n, dec_even);
+            }
+        });
+    }
+
+    #[test]
+    fn test_fibonacci_values_equal() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            // F(1) = F(2) although 1 != 2.
+            for (m, n, expected) in [(1u16, 2u16, true), (3, 4, false), (7, 7, true)] {
+                let enc_m = harness.encrypt(m);
+                let enc_n = harness.encrypt(n);
+                let enc_equal = fibonacci_values_equal(&enc_m, &enc_n, harness.indices(), harness.fibs());
+                let dec_equal = harness.decrypt(&enc_equal);
+
+                assert_eq!(dec_equal, expected, "F({}) == F({}) mismatch: encrypted = {}", m, n, dec_equal);
+            }
+        });
+    }
+
+    #[test]
+    fn test_index_matches() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            for n in [0u16, 7] {
+                let enc_n = harness.encrypt(n);
+                for i in [0, 7, usize::from(MAX_FIBONACCI_INDEX)] {
+                    let helper = harness.decrypt(&index_matches(&enc_n, harness.indices(), i));
+                    let inline = harness.decrypt(&enc_n.eq(&harness.indices()[i]));
+                    assert_eq!(helper, inline, "mismatch for n = {}, i = {}", n, i);
+                    assert_eq!(helper, usize::from(n) == i);
+                }
+            }
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "past the end")]
+    fn test_index_matches_out_of_bounds() {
+        let harness = TestHarness::new();
+        index_matches(&harness.indices()[0], harness.indices(), harness.indices().len());
+    }
+
+    #[test]
+    fn test_fibonacci_next() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            for (n, expected) in [(7u16, 21u16), (0, 1), (MAX_FIBONACCI_INDEX, 0)] {
+                let enc_n = harness.encrypt(n);
+                let dec: u16 = harness.decrypt(&fibonacci_next(&enc_n, harness.indices(), harness.fibs()));
+                assert_eq!(dec, expected, "F({} + 1) mismatch", n);
+            }
+        });
+    }
+
+    #[test]
+    fn test_fibonacci_diff() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            for n in [5u16, 0, MAX_FIBONACCI_INDEX] {
+                let enc_n = harness.encrypt(n);
+                let dec: u16 = harness.decrypt(&fibonacci_diff(&enc_n, harness.indices(), harness.fibs()));
+                // F(n+1) - F(n) = F(n-1), with F(-1) = 1.
+                let expected = n.checked_sub(1).map_or(1, fibonacci_plaintext);
+                assert_eq!(dec, expected, "F({0} + 1) - F({0}) mismatch", n);
+            }
+            // Past the table, the pair falls back to (F(0), F(1)), as for n = 0.
+            let past = harness.encrypt(MAX_FIBONACCI_INDEX + 1);
+            let dec: u16 = harness.decrypt(&fibonacci_diff(&past, harness.indices(), harness.fibs()));
+            assert_eq!(dec, 1);
+        });
+    }
+
+    #[test]
+    fn test_fibonacci_sum_of_two() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            for (n, m) in [(10u16, 11u16), (23, 23), (MAX_FIBONACCI_INDEX, 23)] {
+                let enc_n = harness.encrypt(n);
+                let enc_m = harness.encrypt(m);
+                let (enc_sum, enc_overflow) = fibonacci_sum_of_two(&enc_n, &enc_m, harness.indices(), harness.fibs());
+                let dec_sum: u16 = harness.decrypt(&enc_sum);
+                let dec_overflow = harness.decrypt(&enc_overflow);
+
+                let expected = fibonacci_plaintext(n).overflowing_add(fibonacci_plaintext(m));
+                assert_eq!((dec_sum, dec_overflow), expected, "F({}) + F({}) mismatch", n, m);
+            }
+        });
+    }
+
+    #[test]
+    fn test_fibonacci_select_index() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            let enc_n = harness.encrypt(6u16);
+            let enc_m = harness.encrypt(10u16);
+            for (cond, expected) in [(true, 8u16), (false, 55)] {
+                let enc_cond = FheBool::encrypt(cond, harness.client_key());
+                let enc = fibonacci_select_index(&enc_cond, &enc_n, &enc_m, harness.indices(), harness.fibs());
+                let dec: u16 = harness.decrypt(&enc);
+                assert_eq!(dec, expected, "mismatch for cond = {}", cond);
+            }
+        });
+    }
+
+    #[test]
+    fn test_zeckendorf_mask() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            let mask = zeckendorf_mask(17, harness.fibs());
+            let used: Vec<usize> = mask
+                .iter()
+                .enumerate()
+                .filter(|(_, flag)| harness.decrypt(*flag))
+                .map(|(i, _)| i)
+                .collect();
+
+            // 17 = 13 + 3 + 1
+            assert_eq!(used, [2, 4, 7]);
+            assert_eq!(mask.len(), harness.fibs().len());
+        });
+    }
+
+    #[test]
+    fn test_fibonacci_lookup_square_indices() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            for (n, expected) in [(9u16, 34u16), (10, 0), (16, 987), (MAX_FIBONACCI_INDEX, 0)] {
+                let encrypted = harness.encrypt(n);
+                let dec_lt: u16 =
+                    harness.decrypt(&fibonacci_lookup_square_indices(&encrypted, harness.indices(), harness.fibs()));
+
+                assert_eq!(dec_lt, expected, "Square-index lookup mismatch for n = {}", n);
+            }
+        });
+    }
+
+    #[test]
+    fn test_fibonacci_is_prime_value() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            let is_prime = |v: u16| v >= 2 && (2..v).take_while(|d| d * d <= v).all(|d| !v.is_multiple_of(d));
+            for n in 0u16..=MAX_FIBONACCI_INDEX {
+                let encrypted = harness.encrypt(n);
+                let dec_prime = harness.decrypt(&fibonacci_is_prime_value(&encrypted, harness.indices()));
+
+                let expected = is_prime(fibonacci_plaintext(n));
+                assert_eq!(
+                    dec_prime, expected,
+                    "Primality mismatch for n = {}: encrypted = {}, plaintext = {}",
+                    n, dec_prime, expected
+                );
+            }
+        });
+    }
+
+    #[test]
+    fn test_match_flag_bitmask() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            for (n, expected) in [(5u16, 32u32), (0, 1), (MAX_FIBONACCI_INDEX, 1 << 24), (30, 0)] {
+                let encrypted = harness.encrypt(n);
+                let dec_mask: u32 = harness.decrypt(&match_flag_bitmask(&encrypted, harness.indices()));
+
+                assert_eq!(dec_mask, expected, "Bitmask mismatch for n = {}: encrypted = {:#b}", n, dec_mask);
+            }
+        });
+    }
+
+    #[test]
+    fn test_tables_match_sequential_build() {
+        let harness = TestHarness::new();
+
+        // Whichever path this build uses (rayon with `parallel`, plain iterators
+        // without), it must decrypt to the same tables as a sequential build.
+        let sequential_fibs: Vec<FheUint16> = build_fibonacci_table_plain()
+            .into_iter()
+            .map(|v| FheUint16::encrypt(v, harness.pks()))
+            .collect();
+        let decrypt_all =
+            |table: &[FheUint16]| -> Vec<u16> { table.iter().map(|ct| harness.decrypt(ct)).collect() };
+
+        assert_eq!(decrypt_all(&build_encrypted_fibs(harness.pks())), decrypt_all(&sequential_fibs));
+        let indices: Vec<u16> = (0..=MAX_FIBONACCI_INDEX).collect();
+        assert_eq!(decrypt_all(&build_encrypted_indices(harness.pks())), indices);
+    }
+
+    #[test]
+    fn test_fibonacci_table() {
+        let table_u16 = fibonacci_table::<u16>(FIBONACCI_TABLE_LEN, Overflow::Checked);
+        assert_eq!(table_u16, (0..=MAX_FIBONACCI_INDEX).map(fibonacci_plaintext).collect::<Vec<_>>());
+
+        let table_u32 = fibonacci_table::<u32>(48, Overflow::Checked);
+        assert_eq!(table_u32, (0..=MAX_FIBONACCI_INDEX_U32).map(fibonacci_plaintext_u32).collect::<Vec<_>>());
+        assert_eq!(table_u32.last(), Some(&2_971_215_073));
+
+        // F(25) = 75025 wraps to 75025 - 65536.
+        let wrapped = fibonacci_table::<u16>(FIBONACCI_TABLE_LEN + 1, Overflow::Wrapping);
+        assert_eq!(wrapped.last(), Some(&9489));
+        let saturated = fibonacci_table::<u16>(FIBONACCI_TABLE_LEN + 2, Overflow::Saturating);
+        assert_eq!(saturated[FIBONACCI_TABLE_LEN - 1..], [46368, u16::MAX, u16::MAX]);
+        assert!(fibonacci_table::<u64>(0, Overflow::Checked).is_empty());
+        assert_eq!(fibonacci_table::<u64>(1, Overflow::Checked), [0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "F(25) does not fit in u16")]
+    fn test_fibonacci_table_checked_overflow() {
+        fibonacci_table::<u16>(FIBONACCI_TABLE_LEN + 1, Overflow::Checked);
+    }
+
+    #[test]
+    fn test_build_with_progress() {
+        // Encryption only needs the public key, no server key is set.
+        let harness = TestHarness::new();
+
+        let total = usize::from(MAX_FIBONACCI_INDEX) + 1;
+        let calls = AtomicUsize::new(0);
+        let max_done = AtomicUsize::new(0);
+        let encrypted_fibs = build_encrypted_fibs_with_progress(harness.pks(), |done, reported_total| {
+            assert_eq!(reported_total, total);
+            calls.fetch_add(1, Ordering::Relaxed);
+            max_done.fetch_max(done, Ordering::Relaxed);
+        });
+
+        assert_eq!(calls.into_inner(), total);
+        assert_eq!(max_done.into_inner(), total);
+        let decrypted: Vec<u16> = encrypted_fibs.iter().map(|ct| harness.decrypt(ct)).collect();
+        assert_eq!(decrypted, build_fibonacci_table_plain());
+    }
+
+    #[test]
+    fn test_fibonacci_lookup_plain_index() {
+        // No homomorphic operation: neither a server key nor an encrypted index.
+        let harness = TestHarness::new();
+        let dec_lt: u16 = harness.decrypt(&fibonacci_lookup_plain_index(12, harness.fibs()));
+
+        assert_eq!(dec_lt, 144);
+    }
+
+    #[test]
+    fn test_fibonacci_lookup_scalar_index() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            for n in 0..=MAX_FIBONACCI_INDEX {
+                let encrypted = harness.encrypt(n);
+                let dec_lt: u16 = harness.decrypt(&fibonacci_lookup_scalar_index(&encrypted, harness.fibs()));
+
+                assert_eq!(dec_lt, fibonacci_plaintext(n), "Scalar-index lookup mismatch for n = {}", n);
+            }
+        });
+    }
+
+    #[test]
+    fn test_build_encrypted_indices_with_client() {
+        let harness = TestHarness::new();
+
+        let encrypted_indices = build_encrypted_indices_with_client(harness.client_key());
+
+        let decrypted: Vec<u16> = encrypted_indices.iter().map(|ct| harness.decrypt(ct)).collect();
+        assert_eq!(decrypted, (0..=MAX_FIBONACCI_INDEX).collect::<Vec<_>>());
+    }
+
+    proptest! {
+        // FHE ops are slow: few cases, fixed seed for reproducible runs.
+        #![proptest_config(ProptestConfig {
+            cases: 8,
+            rng_seed: RngSeed::Fixed(24),
+            failure_persistence: None,
+            ..ProptestConfig::default()
+        })]
+
+        #[test]
+        fn prop_fibonacci_strategies(n in prop_oneof![Just(0u16), Just(MAX_FIBONACCI_INDEX), 0..=MAX_FIBONACCI_INDEX]) {
+            let harness = TestHarness::new();
+
+            let encrypted = harness.encrypt(n);
+            let (enc_add, enc_lt) = harness.run(|| {
+                let enc_add = fibonacci_additions(&encrypted, harness.pks());
+                let enc_lt = fibonacci_lookup_with_tables(&encrypted, harness.indices(), harness.fibs());
+                (enc_add, enc_lt)
+            });
+            let dec_add: u16 = harness.decrypt(&enc_add);
+            let dec_lt: u16 = harness.decrypt(&enc_lt);
+
+            let expected = fibonacci_plaintext(n);
+            prop_assert_eq!(dec_add, expected, "Additions mismatch for n = {}", n);
+            prop_assert_eq!(dec_lt, expected, "Lookup mismatch for n = {}", n);
+        }
+    }
+}
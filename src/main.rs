@@ -1,195 +1,757 @@
-use tfhe::{ConfigBuilder, generate_keys, set_server_key, FheUint16, PublicKey};
+use fib_fhe::cli::{parse_args, Args, OutputFormat, Radix, SequenceChoice, StrategyChoice, USAGE};
+use fib_fhe::client::decrypt_value;
+use fib_fhe::csv_log::{append_row, CsvRow};
+use fib_fhe::input::{read_input, read_with_retries, InputError};
+use fib_fhe::negafibonacci::{
+    build_encrypted_negafibs, build_encrypted_signed_indices, negafibonacci_lookup_with_tables,
+    negafibonacci_plaintext,
+};
+use fib_fhe::estimate::estimate_ops;
+use fib_fhe::run::{compute_strategies_cancellable, strategy_recommendation, Selection};
+use fib_fhe::serialization::{serialized_size, table_memory_report};
+use fib_fhe::server::serve_lookup;
+use fib_fhe::tables::{load_or_build_tables, tables_path};
+use fib_fhe::keys::{
+    generate_compressed_keys, generate_keys_seeded, generate_keys_with, load_or_generate_compressed_keys,
+    load_or_generate_keys, load_server_keys,
+};
+use fib_fhe::sequence::{
+    build_encrypted_table_u8, build_encrypted_table_with_progress, build_trivial_table, lookup_with_tables,
+    lookup_with_tables_u8, Fibonacci, Lucas, Pell, Sequence, Tribonacci,
+};
+use fib_fhe::{
+    build_encrypted_fibs_with_progress, build_encrypted_indices_with_progress, build_trivial_fibs,
+    build_trivial_indices, encrypt_inputs, fibonacci_lookup_batch, fibonacci_plaintext, with_server_key, Cancelled,
+    FheInt16, FheUint16, FheUint8, OpCounts, PublicKey,
+};
+use tfhe::{ClientKey, ServerKey};
 use tfhe::prelude::*;
+use log::{debug, error, info, log_enabled, warn, Level};
+use serde::Serialize;
 use std::io::{self, Write};
-use rayon::prelude::*;
-use std::time::Instant;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
-/// Maximum supported index for 16-bit Fibonacci; `F(25) = 75025` > `u16::MAX`.
-const MAX_FIBONACCI_INDEX: u16 = 24;
+/// Set by Ctrl-C during a query, which stops at its next check point: between
+/// two iterations of the additions, or before a strategy starts.
+static CANCELLED: AtomicBool = AtomicBool::new(false);
 
-/// Read a `u16` in the range `0..=24` from stdin.
-///
-/// Returns a `ParseIntError` if parsing fails; the caller is expected to retry.
-fn get_number_input() -> io::Result<u16> {
-    print!("Enter a number (0-{}): ", MAX_FIBONACCI_INDEX);
-    io::stdout().flush()?;
-
-    let mut input = String::new();
-    io::stdin().read_line(&mut input)?;
-    input
-        .trim()
-        .parse::<u16>()
-        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
-}
-
-/// Iterative homomorphic additions with encrypted index-selection.
-/// Builds encrypted indices internally, then iterates with homomorphic additions.
-fn fibonacci_additions(n: &FheUint16, pks: &PublicKey) -> FheUint16 {
-    let encrypted_indices = build_encrypted_indices(pks);
-
-    // Initialize result with F(0) or F(1) depending on whether n == 0.
-    let n_is_0 = n.eq(&encrypted_indices[0]);
-    let mut a = encrypted_indices[0].clone();
-    let mut b = encrypted_indices[1].clone();
-    let mut result = n_is_0.select(&a, &b);
-
-    for i in 2..=MAX_FIBONACCI_INDEX {
-        let next_fib = a + b.clone();
-        a = b;
-        b = next_fib.clone();
-        let i_encrypted = encrypted_indices[usize::from(i)].clone();
-        let n_is_i = n.eq(&i_encrypted);
-        // Use encrypted equality + select to multiplex the running result
-        // without data-dependent control flow.
-        result = n_is_i.select(&next_fib,&result);
-    }
-
-    result
-}
-
-/// Build a plaintext Fibonacci table up to MAX_FIBONACCI_INDEX.
-fn build_fibonacci_table_plain() -> Vec<u16> {
-    let mut fibs = Vec::with_capacity(usize::from(MAX_FIBONACCI_INDEX) + 1);
-    let mut a: u16 = 0;
-    let mut b: u16 = 1;
-    fibs.push(a);
-    for _ in 1..=MAX_FIBONACCI_INDEX {
-        // invariant: a = F(k), b = F(k+1)
-        fibs.push(b);
-        let next = a.wrapping_add(b);
-        a = b;
-        b = next;
-    }
-    fibs
-}
-
-/// Build encrypted indices with parallelization.
-fn build_encrypted_indices(pks: &PublicKey) -> Vec<FheUint16> {
-    (0..=MAX_FIBONACCI_INDEX)
-        .into_par_iter()
-        .map(|i| FheUint16::encrypt(i, pks))
-        .collect()
-}
-
-/// Build encrypted Fibonacci table from plaintext with parallelization.
-fn build_encrypted_fibs(pks: &PublicKey) -> Vec<FheUint16> {
-    let fibs_plain = build_fibonacci_table_plain();
-    fibs_plain
-        .par_iter()
-        .copied()
-        .map(|v| FheUint16::encrypt(v, pks))
-        .collect()
-}
-
-/// Lookup over an encrypted table
-/// equality + select, reusing prebuilt tables.
-fn fibonacci_lookup_with_tables(
-    n: &FheUint16,
-    encrypted_indices: &[FheUint16],
-    encrypted_fibs: &[FheUint16],
-) -> FheUint16 {
-    let mut result = encrypted_fibs[0].clone();
-    for i in 1..=usize::from(MAX_FIBONACCI_INDEX) {
-        let is_match = n.eq(&encrypted_indices[i]);
-        result = is_match.select(&encrypted_fibs[i], &result);
-    }
-    result
-}
-
-/// Plaintext reference implementation used for verification.
-fn fibonacci_plaintext(n: u16) -> u16 {
-    let mut a = 0;
-    let mut b = 1;
-    for _ in 0..n {
-        let tmp = a + b;
-        a = b;
-        b = tmp;
-    }
-    a
+/// Whether a query is computing; Ctrl-C outside of one exits right away.
+static COMPUTING: AtomicBool = AtomicBool::new(false);
+
+/// Turn Ctrl-C into a clean exit through [`exit_cancelled`], deferred to the
+/// next check point while a query computes. A second Ctrl-C does not wait.
+fn install_interrupt_handler() {
+    let result = ctrlc::set_handler(|| {
+        if !COMPUTING.load(Ordering::SeqCst) || CANCELLED.swap(true, Ordering::SeqCst) {
+            exit_cancelled();
+        }
+    });
+    if let Err(e) = result {
+        warn!("Failed to install the Ctrl-C handler: {}", e);
+    }
 }
 
-fn main() {
-    let config = ConfigBuilder::default().build();
+/// Report the interruption and exit with the conventional code of SIGINT.
+fn exit_cancelled() -> ! {
+    // Ends a prompt or progress line, which has no newline yet.
+    eprintln!("\n{}", Cancelled);
+    std::process::exit(130);
+}
+
+/// Exit through [`exit_cancelled`] if Ctrl-C was pressed during the query.
+fn check_cancelled() {
+    if CANCELLED.load(Ordering::SeqCst) {
+        exit_cancelled();
+    }
+}
+
+/// Prompt for an index in `0..=max` and read it from stdin, or `None` once the
+/// user types `quit`; a closed stdin is [`InputError::Eof`].
+fn get_number_input(max: u16) -> Result<Option<u16>, InputError> {
+    // On stderr, like the logs: stdout only carries results.
+    eprint!("Enter a number (0-{}), or `quit`: ", max);
+    io::stderr().flush()?;
 
+    read_input(io::stdin().lock(), max)
+}
+
+/// Compute every index of `batch` against one pair of encrypted tables.
+fn run_batch(batch: &[u16], radix: Radix, client_key: &ClientKey, server_key: &ServerKey, tables: &Tables) {
     // Client-side
-    let (client_key, server_key) = generate_keys(config);
-    let pks = PublicKey::new(&client_key);
-
-    // Get user input for the first number
-    let clear_a = loop {
-        match get_number_input() {
-            Ok(num) => break num,
-            Err(_) => println!("Invalid input. Please enter a number between 0 and 24."),
-        }
-    };
-    println!("You entered: {}", clear_a);
-    let a = FheUint16::encrypt(clear_a, &client_key);
+    let inputs = encrypt_inputs(batch, client_key);
 
     // Server-side
-    set_server_key(server_key);
-    println!("Computing Fibonacci with two strategies...");
+    let t_batch = Instant::now();
+    let results = fibonacci_lookup_batch(&inputs, &tables.indices, &tables.values, server_key);
+    let dur_batch = t_batch.elapsed();
 
-    // One-time setup (parallelizable, public-key side)
-    let t_setup_start = Instant::now();
-    let encrypted_indices = build_encrypted_indices(&pks);
-    let encrypted_fibs = build_encrypted_fibs(&pks);
-    let dur_setup = t_setup_start.elapsed();
+    // Client-side
+    debug!("Setup (lookup tables): {} ms", tables.setup_ms);
+    debug!("Batch lookup: {} ms for {} queries", dur_batch.as_millis(), batch.len());
+    for (n, result) in batch.iter().zip(&results) {
+        let decrypted = decrypt_value::<u16>(result, client_key);
+        debug!("F({}) = {} (expected {})", n, decrypted, fibonacci_plaintext(*n));
+        println!("{}", radix.format(decrypted));
+    }
+}
+
+/// Timings and decrypted results of one run; strategies that did not run are `None`.
+#[derive(Debug, Default, Serialize)]
+struct Report {
+    input: u16,
+    expected: u16,
+    /// Serialized size of the encrypted input.
+    index_bytes: Option<usize>,
+    additions_result: Option<u16>,
+    additions_ms: Option<u128>,
+    additions_bytes: Option<usize>,
+    setup_ms: Option<u128>,
+    lookup_result: Option<u16>,
+    lookup_ms: Option<u128>,
+    lookup_bytes: Option<usize>,
+    matrix_result: Option<u16>,
+    matrix_ms: Option<u128>,
+    matrix_bytes: Option<usize>,
+    /// Faster of additions and lookup, when both ran.
+    recommendation: Option<&'static str>,
+}
+
+impl Report {
+    /// Append the report to the `--log-csv` file, if any; a failure is logged and
+    /// does not stop the session.
+    fn log_csv(&self, args: &Args) {
+        let Some(path) = &args.log_csv else { return };
+        let row = CsvRow {
+            additions_result: self.additions_result,
+            additions_ms: self.additions_ms,
+            lookup_result: self.lookup_result,
+            lookup_ms: self.lookup_ms,
+            ..CsvRow::now(self.input, self.expected)
+        };
+        if let Err(e) = append_row(path, &row) {
+            warn!("Failed to append to {}: {}", path.display(), e);
+        }
+    }
+
+    fn print(&self, format: OutputFormat, radix: Radix) {
+        match format {
+            OutputFormat::Json => println!("{}", serde_json::to_string(self).expect("report serializes to JSON")),
+            OutputFormat::Text => {
+                if let Some(bytes) = self.index_bytes {
+                    debug!("Encrypted index: {} bytes", bytes);
+                }
+                if let (Some(ms), Some(result)) = (self.additions_ms, self.additions_result) {
+                    debug!("Additions: {} ms, result {}{}", ms, result, bytes_suffix(self.additions_bytes));
+                }
+                if let Some(ms) = self.setup_ms {
+                    debug!("Setup (lookup tables): {} ms", ms);
+                }
+                if let (Some(ms), Some(result)) = (self.lookup_ms, self.lookup_result) {
+                    debug!(
+                        "Lookup (uses setup): compute-only: {} ms, result {}{}",
+                        ms,
+                        result,
+                        bytes_suffix(self.lookup_bytes)
+                    );
+                }
+                if let (Some(ms), Some(result)) = (self.matrix_ms, self.matrix_result) {
+                    debug!("Matrix power: {} ms, result {}{}", ms, result, bytes_suffix(self.matrix_bytes));
+                }
+                debug!("Expected: {}", self.expected);
 
-    // One-shot baseline: additions (builds indices internally)
-    let t_add_total = Instant::now();
-    let result_add = fibonacci_additions(&a, &pks);
-    let dur_add_total = t_add_total.elapsed();
+                let results = [
+                    ("Lookup", self.lookup_result),
+                    ("Additions", self.additions_result),
+                    ("Matrix power", self.matrix_result),
+                ];
+                for (strategy, result) in results.iter().filter_map(|(name, result)| Some((name, (*result)?))) {
+                    if result != self.expected {
+                        warn!("{} result {} does not match the expected {}", strategy, result, self.expected);
+                    }
+                }
+                // All strategies agree on a correct run: print a single number.
+                if let Some(result) = results.iter().find_map(|(_, result)| *result) {
+                    println!("{}", radix.format(result));
+                }
+                // On stderr, so that stdout stays the single number; the default
+                // log filter would hide it at `info`.
+                if let Some(strategy) = self.recommendation {
+                    eprintln!("Recommended strategy: {} (faster per query, excluding table setup)", strategy);
+                }
+            }
+        }
+    }
+}
+
+/// ` (<n> bytes)` after a result whose serialized size is known.
+fn bytes_suffix(bytes: Option<usize>) -> String {
+    bytes.map(|bytes| format!(" ({} bytes)", bytes)).unwrap_or_default()
+}
 
+/// Encrypted lookup tables, built once and reused by every query.
+struct Tables {
+    indices: Vec<FheUint16>,
+    values: Vec<FheUint16>,
+    /// 8-bit table for `--u8`, which leaves `values` empty; empty otherwise.
+    values_u8: Vec<FheUint8>,
+    setup_ms: u128,
+}
+
+impl Tables {
+    fn build(sequence: SequenceChoice, pks: &PublicKey) -> Self {
+        // One-time setup (parallelizable, public-key side)
+        let t_setup_start = Instant::now();
+        let (indices, values) = Self::encrypt(sequence, pks);
+        let setup_ms = t_setup_start.elapsed().as_millis();
+        Tables { indices, values, values_u8: Vec::new(), setup_ms }
+    }
+
+    /// Load the tables cached in `dir`, or build and cache them there.
+    fn load_or_build(dir: &Path, sequence: SequenceChoice, pks: &PublicKey) -> io::Result<Self> {
+        let t_setup_start = Instant::now();
+        let path = tables_path(dir, sequence.name());
+        let values_len = usize::from(sequence.max_index()) + 1;
+        let (indices, values) = load_or_build_tables(path, values_len, || Self::encrypt(sequence, pks))?;
+        let setup_ms = t_setup_start.elapsed().as_millis();
+        Ok(Tables { indices, values, values_u8: Vec::new(), setup_ms })
+    }
+
+    fn encrypt(sequence: SequenceChoice, pks: &PublicKey) -> (Vec<FheUint16>, Vec<FheUint16>) {
+        let indices = build_encrypted_indices_with_progress(pks, progress_line("Encrypting indices"));
+        end_progress_line();
+        let progress = progress_line("Encrypting table");
+        let values = match sequence {
+            SequenceChoice::Fibonacci => build_encrypted_fibs_with_progress(pks, progress),
+            SequenceChoice::Lucas => build_encrypted_table_with_progress::<Lucas>(pks, progress),
+            SequenceChoice::Pell => build_encrypted_table_with_progress::<Pell>(pks, progress),
+            SequenceChoice::Tribonacci => build_encrypted_table_with_progress::<Tribonacci>(pks, progress),
+            SequenceChoice::Negafibonacci => unreachable!("negafibonacci has its own signed tables"),
+        };
+        end_progress_line();
+        (indices, values)
+    }
+
+    /// Insecure, test-only tables of trivial ciphertexts; requires the server key
+    /// to be set.
+    fn build_trivial(sequence: SequenceChoice) -> Self {
+        let t_setup_start = Instant::now();
+        let indices = build_trivial_indices();
+        let values = match sequence {
+            SequenceChoice::Fibonacci => build_trivial_fibs(),
+            SequenceChoice::Lucas => build_trivial_table::<Lucas>(),
+            SequenceChoice::Pell => build_trivial_table::<Pell>(),
+            SequenceChoice::Tribonacci => build_trivial_table::<Tribonacci>(),
+            SequenceChoice::Negafibonacci => unreachable!("--trivial is rejected for negafibonacci"),
+        };
+        let setup_ms = t_setup_start.elapsed().as_millis();
+        Tables { indices, values, values_u8: Vec::new(), setup_ms }
+    }
+
+    /// Tables for `--u8`: 16-bit indices and an 8-bit table of values.
+    fn build_u8(sequence: SequenceChoice, pks: &PublicKey) -> Self {
+        let t_setup_start = Instant::now();
+        let indices = build_encrypted_indices_with_progress(pks, progress_line("Encrypting indices"));
+        end_progress_line();
+        let values_u8 = match sequence {
+            SequenceChoice::Fibonacci => build_encrypted_table_u8::<Fibonacci>(pks),
+            SequenceChoice::Lucas => build_encrypted_table_u8::<Lucas>(pks),
+            SequenceChoice::Pell => build_encrypted_table_u8::<Pell>(pks),
+            SequenceChoice::Tribonacci => build_encrypted_table_u8::<Tribonacci>(pks),
+            SequenceChoice::Negafibonacci => unreachable!("--u8 is rejected for negafibonacci"),
+        };
+        let setup_ms = t_setup_start.elapsed().as_millis();
+        Tables { indices, values: Vec::new(), values_u8, setup_ms }
+    }
+}
+
+/// Compute the `clear_a`-th term of `S` with the lookup strategy.
+fn run_lookup<S: Sequence>(clear_a: u16, a: &FheUint16, client_key: &ClientKey, tables: &Tables) -> Report {
+    check_cancelled();
     let t_lt_compute = Instant::now();
-    let result_lt = fibonacci_lookup_with_tables(&a, &encrypted_indices, &encrypted_fibs);
+    let result_lt = record_lookup(|| lookup_with_tables::<S>(a, &tables.indices, &tables.values));
     let dur_lt_compute = t_lt_compute.elapsed();
 
     // Client-side
-    let decrypted_add: u16 = result_add.decrypt(&client_key);
-    let decrypted_lt: u16 = result_lt.decrypt(&client_key);
-    let expected = fibonacci_plaintext(clear_a);
-
-    println!("Additions: {} ms, result {}", dur_add_total.as_millis(), decrypted_add);
-    println!("Setup (lookup tables): {} ms", dur_setup.as_millis());
-    println!("Lookup (uses setup): compute-only: {} ms, result {}", dur_lt_compute.as_millis(), decrypted_lt);
-    println!("Expected: {}", expected);
-}
-
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_diff_fibonacci() {
-        let config = ConfigBuilder::default().build();
-        let (client_key, server_key) = generate_keys(config);
-        let pks = PublicKey::new(&client_key);
-
-        set_server_key(server_key);
-
-        // Test for a range of small n
-        for n in 0u16..=10 {
-            let encrypted = FheUint16::encrypt(n, &client_key);
-            let enc_add = fibonacci_additions(&encrypted, &pks);
-            let encrypted_indices = build_encrypted_indices(&pks);
-            let encrypted_fibs = build_encrypted_fibs(&pks);
-            let enc_lt = fibonacci_lookup_with_tables(&encrypted, &encrypted_indices, &encrypted_fibs);
-            let dec_add: u16 = enc_add.decrypt(&client_key);
-            let dec_lt: u16 = enc_lt.decrypt(&client_key);
-
-            let expected = fibonacci_plaintext(n);
-
-            assert_eq!(
-                dec_add, expected,
-                "Additions mismatch for n = {}: encrypted = {}, plaintext = {}",
-                n, dec_add, expected
-            );
-            assert_eq!(
-                dec_lt, expected,
-                "Lookup mismatch for n = {}: encrypted = {}, plaintext = {}",
-                n, dec_lt, expected
-            );
+    Report {
+        input: clear_a,
+        expected: S::plaintext(clear_a),
+        lookup_result: Some(result_lt.decrypt(client_key)),
+        lookup_ms: Some(dur_lt_compute.as_millis()),
+        lookup_bytes: serialized_size(&result_lt).ok(),
+        ..Report::default()
+    }
+}
+
+/// [`run_lookup`] over the 8-bit table of `--u8`.
+fn run_lookup_u8<S: Sequence>(clear_a: u16, a: &FheUint16, client_key: &ClientKey, tables: &Tables) -> Report {
+    check_cancelled();
+    let t_lt_compute = Instant::now();
+    let result_lt = record_lookup(|| lookup_with_tables_u8::<S>(a, &tables.indices, &tables.values_u8));
+    let dur_lt_compute = t_lt_compute.elapsed();
+
+    // Client-side
+    let result: u8 = result_lt.decrypt(client_key);
+    Report {
+        input: clear_a,
+        expected: S::plaintext(clear_a),
+        lookup_result: Some(u16::from(result)),
+        lookup_ms: Some(dur_lt_compute.as_millis()),
+        lookup_bytes: serialized_size(&result_lt).ok(),
+        ..Report::default()
+    }
+}
+
+/// Run the lookup `compute` of a lookup-only sequence, logging its homomorphic
+/// operations with the `stats` feature.
+fn record_lookup<T>(compute: impl FnOnce() -> T) -> T {
+    #[cfg(feature = "stats")]
+    {
+        let (result, counts) = fib_fhe::stats::record(compute);
+        log_op_counts("lookup", counts);
+        result
+    }
+    #[cfg(not(feature = "stats"))]
+    compute()
+}
+
+/// Log the homomorphic operations of one strategy, one line per strategy.
+#[cfg(feature = "stats")]
+fn log_op_counts(strategy: &str, counts: OpCounts) {
+    info!("Homomorphic ops ({}): {}", strategy, counts);
+}
+
+/// Compute `F(clear_a)` with the strategies selected by `args`; `tables` is
+/// required for the lookup strategy.
+fn run_fibonacci(
+    clear_a: u16,
+    a: &FheUint16,
+    args: &Args,
+    client_key: &ClientKey,
+    pks: &PublicKey,
+    tables: Option<&Tables>,
+) -> Report {
+    let tables = tables.map(|tables| (&tables.indices[..], &tables.values[..]));
+    let (ciphertexts, timings) = compute_strategies_cancellable(a, pks, tables, args.selection(), &CANCELLED)
+        .unwrap_or_else(|Cancelled| exit_cancelled());
+    #[cfg(feature = "stats")]
+    for (strategy, counts) in [
+        ("additions", ciphertexts.op_counts.additions),
+        ("lookup", ciphertexts.op_counts.lookup),
+        ("matrix", ciphertexts.op_counts.matrix),
+    ] {
+        if let Some(counts) = counts {
+            log_op_counts(strategy, counts);
+        }
+    }
+    #[cfg(feature = "profile")]
+    if let Some(summary) = ciphertexts.additions_iterations.as_deref().and_then(fib_fhe::profile::Summary::of) {
+        info!("Additions iterations: {}", summary);
+    }
+
+    // Client-side
+    let results = ciphertexts.decrypt(clear_a, client_key);
+    let ms = |duration: Option<Duration>| duration.map(|duration| duration.as_millis());
+    let bytes = |ct: &Option<FheUint16>| ct.as_ref().and_then(|ct| serialized_size(ct).ok());
+    Report {
+        input: clear_a,
+        expected: results.expected,
+        additions_result: results.additions,
+        additions_ms: ms(timings.additions),
+        additions_bytes: bytes(&ciphertexts.additions),
+        lookup_result: results.lookup,
+        lookup_ms: ms(timings.lookup),
+        lookup_bytes: bytes(&ciphertexts.lookup),
+        matrix_result: results.matrix,
+        matrix_ms: ms(timings.matrix),
+        matrix_bytes: bytes(&ciphertexts.matrix),
+        recommendation: (timings.additions.is_some() && timings.lookup.is_some())
+            .then(|| strategy_recommendation(&timings)),
+        ..Report::default()
+    }
+}
+
+/// Encrypt `clear_a`, compute it with the selected sequence and strategies, and
+/// decrypt the results. The server key must already be set.
+fn run_query(
+    clear_a: u16,
+    args: &Args,
+    client_key: &ClientKey,
+    pks: &PublicKey,
+    tables: Option<&Tables>,
+) -> Report {
+    // Client-side
+    let a = FheUint16::encrypt(clear_a, client_key);
+
+    // Server-side
+    // A Ctrl-C that came after the last check point of the previous interactive
+    // query must not cancel this one.
+    CANCELLED.store(false, Ordering::SeqCst);
+    COMPUTING.store(true, Ordering::SeqCst);
+    let report = match (args.sequence, tables) {
+        // --u8 only runs the lookup, also for fib.
+        (SequenceChoice::Fibonacci, Some(tables)) if args.u8_output => {
+            run_lookup_u8::<Fibonacci>(clear_a, &a, client_key, tables)
+        }
+        (SequenceChoice::Fibonacci, _) => run_fibonacci(clear_a, &a, args, client_key, pks, tables),
+        (SequenceChoice::Lucas, Some(tables)) if args.u8_output => run_lookup_u8::<Lucas>(clear_a, &a, client_key, tables),
+        (SequenceChoice::Pell, Some(tables)) if args.u8_output => run_lookup_u8::<Pell>(clear_a, &a, client_key, tables),
+        (SequenceChoice::Tribonacci, Some(tables)) if args.u8_output => {
+            run_lookup_u8::<Tribonacci>(clear_a, &a, client_key, tables)
+        }
+        (SequenceChoice::Lucas, Some(tables)) => run_lookup::<Lucas>(clear_a, &a, client_key, tables),
+        (SequenceChoice::Pell, Some(tables)) => run_lookup::<Pell>(clear_a, &a, client_key, tables),
+        (SequenceChoice::Tribonacci, Some(tables)) => run_lookup::<Tribonacci>(clear_a, &a, client_key, tables),
+        (SequenceChoice::Negafibonacci, _) => unreachable!("negafibonacci runs through run_negafibonacci"),
+        (_, None) => unreachable!("tables are always built for lookup-only sequences"),
+    };
+    COMPUTING.store(false, Ordering::SeqCst);
+    Report { index_bytes: serialized_size(&a).ok(), ..report }
+}
+
+/// Progress callback rewriting a `label: done/total` line on stderr.
+///
+/// Callbacks race each other from the rayon workers; the lock makes sure a
+/// smaller count never overwrites a larger one.
+fn progress_line(label: &'static str) -> impl Fn(usize, usize) + Sync {
+    let shown = Mutex::new(0);
+    move |done, total| {
+        if !log_enabled!(Level::Info) {
+            return;
+        }
+        let mut shown = shown.lock().unwrap_or_else(|e| e.into_inner());
+        if done > *shown {
+            *shown = done;
+            eprint!("\r{}: {}/{}", label, done, total);
+        }
+    }
+}
+
+/// Finish a line drawn by [`progress_line`].
+fn end_progress_line() {
+    if log_enabled!(Level::Info) {
+        eprintln!();
+    }
+}
+
+/// Results of a negafibonacci query, which has a signed input and result.
+#[derive(Serialize)]
+struct NegafibonacciReport {
+    input: i16,
+    expected: i16,
+    setup_ms: u128,
+    lookup_result: i16,
+    lookup_ms: u128,
+}
+
+/// Compute `F(clear_a)` for a signed index with the lookup strategy.
+fn run_negafibonacci(clear_a: i16, format: OutputFormat, client_key: &ClientKey, pks: &PublicKey) {
+    // Client-side
+    let a = FheInt16::encrypt(clear_a, client_key);
+
+    // Server-side
+    let t_setup_start = Instant::now();
+    let encrypted_indices = build_encrypted_signed_indices(pks);
+    let encrypted_negafibs = build_encrypted_negafibs(pks);
+    let setup_ms = t_setup_start.elapsed().as_millis();
+
+    let t_lt_compute = Instant::now();
+    let result_lt = negafibonacci_lookup_with_tables(&a, &encrypted_indices, &encrypted_negafibs);
+    let lookup_ms = t_lt_compute.elapsed().as_millis();
+
+    // Client-side
+    let report = NegafibonacciReport {
+        input: clear_a,
+        expected: negafibonacci_plaintext(clear_a),
+        setup_ms,
+        lookup_result: result_lt.decrypt(client_key),
+        lookup_ms,
+    };
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string(&report).expect("report serializes to JSON")),
+        OutputFormat::Text => {
+            debug!("Setup (lookup tables): {} ms", report.setup_ms);
+            debug!("Lookup (uses setup): compute-only: {} ms, result {}", report.lookup_ms, report.lookup_result);
+            debug!("Expected: {}", report.expected);
+            if report.lookup_result != report.expected {
+                warn!("Lookup result {} does not match the expected {}", report.lookup_result, report.expected);
+            }
+            println!("{}", report.lookup_result);
+        }
+    }
+}
+
+/// Build, load or trivially encrypt the lookup tables as selected by `args`.
+fn setup_tables(args: &Args, pks: &PublicKey) -> Tables {
+    let tables = match (&args.tables, args.trivial) {
+        // --u8 is rejected together with --tables and --trivial.
+        _ if args.u8_output => Tables::build_u8(args.sequence, pks),
+        (_, true) => Tables::build_trivial(args.sequence),
+        (Some(dir), false) => Tables::load_or_build(dir, args.sequence, pks).unwrap_or_else(|e| {
+            error!("Failed to load or save tables in {}: {}", dir.display(), e);
+            std::process::exit(1);
+        }),
+        (None, false) => Tables::build(args.sequence, pks),
+    };
+    // One of `values` and `values_u8` is empty.
+    info!(
+        "Table memory: {} bytes of indices, {} bytes of values",
+        table_memory_report(&tables.indices),
+        table_memory_report(&tables.values) + table_memory_report(&tables.values_u8)
+    );
+    tables
+}
+
+/// Duration of each key generation step and serialized size of each key, as
+/// measured by the `keygen` subcommand.
+#[derive(Serialize)]
+struct KeygenReport {
+    /// Client and server keys, with `generate_keys`.
+    generate_keys_ms: u128,
+    public_key_ms: u128,
+    client_key_bytes: Option<usize>,
+    server_key_bytes: Option<usize>,
+    public_key_bytes: Option<usize>,
+}
+
+/// `keygen`: generate the keys of `--params`, report how long each step took and
+/// how large the keys are, and compute nothing.
+fn run_keygen(args: &Args) {
+    let t_generate_keys = Instant::now();
+    let (client_key, server_key) = tfhe::generate_keys(args.config().build());
+    let generate_keys_ms = t_generate_keys.elapsed().as_millis();
+
+    let t_public_key = Instant::now();
+    let public_key = PublicKey::new(&client_key);
+    let public_key_ms = t_public_key.elapsed().as_millis();
+
+    let report = KeygenReport {
+        generate_keys_ms,
+        public_key_ms,
+        client_key_bytes: serialized_size(&client_key).ok(),
+        server_key_bytes: serialized_size(&server_key).ok(),
+        public_key_bytes: serialized_size(&public_key).ok(),
+    };
+    match args.format {
+        OutputFormat::Json => println!("{}", serde_json::to_string(&report).expect("report serializes to JSON")),
+        OutputFormat::Text => {
+            println!("generate_keys: {} ms", report.generate_keys_ms);
+            println!("public key: {} ms", report.public_key_ms);
+            for (key, bytes) in [
+                ("client key", report.client_key_bytes),
+                ("server key", report.server_key_bytes),
+                ("public key", report.public_key_bytes),
+            ] {
+                if let Some(bytes) = bytes {
+                    println!("{}: {} bytes", key, bytes);
+                }
+            }
         }
     }
 }
+
+/// `--plaintext`: the reference value of the selected sequence, with neither keys
+/// nor encryption.
+fn run_plaintext(args: &Args) {
+    let n = args.n.expect("--plaintext requires an index");
+    let expected = match args.sequence {
+        SequenceChoice::Fibonacci => fibonacci_plaintext(n),
+        SequenceChoice::Lucas => Lucas::plaintext(n),
+        SequenceChoice::Pell => Pell::plaintext(n),
+        SequenceChoice::Tribonacci => Tribonacci::plaintext(n),
+        SequenceChoice::Negafibonacci => unreachable!("--plaintext is rejected with --sequence negafib"),
+    };
+    let report = Report { input: n, expected, ..Report::default() };
+    match args.format {
+        OutputFormat::Json => report.print(args.format, args.radix),
+        OutputFormat::Text => println!("{}", args.radix.format(expected)),
+    }
+    report.log_csv(args);
+}
+
+/// Estimated operations of each strategy of a query, as printed by
+/// `--estimate`; `None` for strategies that would not run.
+#[derive(Serialize)]
+struct EstimateReport {
+    additions: Option<OpCounts>,
+    lookup: Option<OpCounts>,
+    matrix: Option<OpCounts>,
+}
+
+/// `--estimate`: the homomorphic operations of every selected strategy, with
+/// neither keys nor encryption.
+fn run_estimate(args: &Args) {
+    let max_index = args.max_index();
+    // Sequences other than Fibonacci only run the lookup.
+    let selection = match args.sequence {
+        SequenceChoice::Fibonacci => args.selection(),
+        _ => Selection { additions: false, lookup: true, matrix: false },
+    };
+    let estimate = |runs: bool, strategy| runs.then(|| estimate_ops(strategy, max_index));
+    let report = EstimateReport {
+        additions: estimate(selection.additions, StrategyChoice::Additions),
+        lookup: estimate(selection.lookup, StrategyChoice::Lookup),
+        matrix: estimate(selection.matrix, StrategyChoice::Matrix),
+    };
+    match args.format {
+        OutputFormat::Json => println!("{}", serde_json::to_string(&report).expect("report serializes to JSON")),
+        OutputFormat::Text => {
+            for (strategy, estimate) in
+                [("additions", report.additions), ("lookup", report.lookup), ("matrix", report.matrix)]
+            {
+                if let Some(estimate) = estimate {
+                    println!("{}: {}", strategy, estimate);
+                }
+            }
+        }
+    }
+}
+
+/// Server-only mode: the client key is never loaded, the input and output are
+/// ciphertexts.
+fn serve_ciphertext(keys_dir: &Path) -> io::Result<()> {
+    let (server_key, pks) = load_server_keys(keys_dir)?;
+    let mut stdout = io::stdout().lock();
+    serve_lookup(io::stdin().lock(), &mut stdout, server_key, &pks)?;
+    stdout.flush()
+}
+
+fn main() {
+    // Quiet by default; RUST_LOG=info or RUST_LOG=debug surfaces progress and timings.
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+
+    let args = parse_args(std::env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("error: {}\n{}", e, USAGE);
+        std::process::exit(1);
+    });
+    install_interrupt_handler();
+
+    if args.keygen {
+        run_keygen(&args);
+        return;
+    }
+
+    if args.plaintext {
+        run_plaintext(&args);
+        return;
+    }
+
+    if args.estimate {
+        run_estimate(&args);
+        return;
+    }
+
+    if args.ciphertext_stdin {
+        let dir = args.keys.as_deref().expect("--ciphertext-stdin requires --keys");
+        if let Err(e) = serve_ciphertext(dir) {
+            error!("Failed to serve the ciphertext on stdin with the keys in {}: {}", dir.display(), e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // Client-side
+    let config = args.config();
+    let keys = match (&args.keys, args.compressed_keys, args.seed) {
+        // --seed is rejected together with --keys and --compressed-keys.
+        (_, _, Some(seed)) => Ok(generate_keys_seeded(config, seed)),
+        (Some(dir), false, None) => load_or_generate_keys(dir, config),
+        (Some(dir), true, None) => load_or_generate_compressed_keys(dir, config),
+        (None, false, None) => Ok(generate_keys_with(config)),
+        (None, true, None) => {
+            let (client_key, compressed_server_key, pks) = generate_compressed_keys(config);
+            Ok((client_key, compressed_server_key.decompress(), pks))
+        }
+    };
+    let (client_key, server_key, pks) = keys.unwrap_or_else(|e| {
+        let dir = args.keys.as_deref().unwrap_or(Path::new("."));
+        error!("Failed to load or save keys in {}: {}", dir.display(), e);
+        std::process::exit(1);
+    });
+
+    match args.threads {
+        #[cfg(feature = "parallel")]
+        Some(threads) => {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap_or_else(|e| {
+                    error!("Failed to build a thread pool with {} threads: {}", threads, e);
+                    std::process::exit(1);
+                });
+            pool.install(|| {
+                with_server_key(server_key.clone(), || run(&args, &client_key, &server_key, &pks))
+            });
+        }
+        _ => with_server_key(server_key.clone(), || run(&args, &client_key, &server_key, &pks)),
+    }
+}
+
+/// Server-side half of `main`: everything that runs on the selected thread pool,
+/// with `server_key` set on the calling thread.
+fn run(args: &Args, client_key: &ClientKey, server_key: &ServerKey, pks: &PublicKey) {
+    if let Some(clear_a) = args.signed_n {
+        info!("Computing the {} sequence...", args.sequence.name());
+        run_negafibonacci(clear_a, args.format, client_key, pks);
+        return;
+    }
+    if let Some(batch) = &args.batch {
+        run_batch(batch, args.radix, client_key, server_key, &setup_tables(args, pks));
+        return;
+    }
+
+    let needs_tables = args.sequence != SequenceChoice::Fibonacci || args.runs_lookup();
+    let tables = needs_tables.then(|| setup_tables(args, pks));
+    let setup_ms = tables.as_ref().map(|tables| tables.setup_ms);
+
+    // Take the index from the command line, or prompt for it
+    if let Some(clear_a) = args.n {
+        info!("Computing the {} sequence...", args.sequence.name());
+        let report = run_query(clear_a, args, client_key, pks, tables.as_ref());
+        let report = Report { setup_ms, ..report };
+        report.print(args.format, args.radix);
+        report.log_csv(args);
+        return;
+    }
+
+    // Interactive session: the server key and tables are reused by every query.
+    let max = args.max_index();
+    let mut first = true;
+    loop {
+        let read = read_with_retries(args.retry_limit(), || get_number_input(max), |e| match e {
+            InputError::OutOfRange { value } => warn!("Invalid input: {} is too large, max is {}.", value, max),
+            _ => warn!("Invalid input: not a number. Please enter a number between 0 and {}.", max),
+        });
+        let clear_a = match read {
+            Ok(Some(num)) => num,
+            Ok(None) => break,
+            Err(InputError::Eof) => {
+                // Ends the prompt line, which has no newline yet.
+                eprintln!("\nEnd of input, exiting.");
+                break;
+            }
+            Err(InputError::Io(e)) => {
+                error!("Failed to read input: {}", e);
+                std::process::exit(1);
+            }
+            Err(e @ (InputError::Parse(_) | InputError::OutOfRange { .. })) => {
+                error!("Giving up after {} invalid inputs, the last one: {}", args.retry_limit(), e);
+                std::process::exit(1);
+            }
+        };
+        info!("You entered: {}", clear_a);
+
+        let t_query = Instant::now();
+        let report = run_query(clear_a, args, client_key, pks, tables.as_ref());
+        let query_ms = t_query.elapsed().as_millis();
+
+        // Setup is only paid once; report it with the first query.
+        let report = if first { Report { setup_ms, ..report } } else { report };
+        first = false;
+        report.print(args.format, args.radix);
+        report.log_csv(args);
+        debug!("Query: {} ms", query_ms);
+    }
+}
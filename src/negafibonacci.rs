@@ -0,0 +1,112 @@
+//! Fibonacci numbers extended to negative indices.
+//!
+//! `F(-n) = (-1)^(n+1) F(n)`, e.g. `F(-6) = -8` and `F(-7) = 13`. Indices and
+//! values are signed, so the tables are `FheInt16` instead of `FheUint16`.
+
+use crate::par::*;
+use crate::{fibonacci_plaintext, index_matches, ops, PublicKey};
+use tfhe::prelude::*;
+use tfhe::FheInt16;
+
+/// Largest `|n|` covered by the negafibonacci tables.
+pub const MAX_NEGAFIBONACCI_INDEX: i16 = 12;
+
+/// Plaintext reference for `F(n)`, `n` in `-MAX_NEGAFIBONACCI_INDEX..=MAX_NEGAFIBONACCI_INDEX`.
+///
+/// # Panics
+///
+/// If `|n| > MAX_NEGAFIBONACCI_INDEX`.
+pub fn negafibonacci_plaintext(n: i16) -> i16 {
+    assert!(
+        n.unsigned_abs() <= MAX_NEGAFIBONACCI_INDEX.unsigned_abs(),
+        "|n| is at most {}",
+        MAX_NEGAFIBONACCI_INDEX
+    );
+    let value = i16::try_from(fibonacci_plaintext(n.unsigned_abs())).expect("|n| is small enough for i16");
+    if n < 0 && n % 2 == 0 { -value } else { value }
+}
+
+/// Build the plaintext table `F(-MAX_NEGAFIBONACCI_INDEX), ..., F(MAX_NEGAFIBONACCI_INDEX)`.
+pub fn build_negafibonacci_table_plain() -> Vec<i16> {
+    (-MAX_NEGAFIBONACCI_INDEX..=MAX_NEGAFIBONACCI_INDEX)
+        .map(negafibonacci_plaintext)
+        .collect()
+}
+
+/// Build encrypted signed indices `-MAX_NEGAFIBONACCI_INDEX..=MAX_NEGAFIBONACCI_INDEX`
+/// with parallelization.
+pub fn build_encrypted_signed_indices(pks: &PublicKey) -> Vec<FheInt16> {
+    (-MAX_NEGAFIBONACCI_INDEX..=MAX_NEGAFIBONACCI_INDEX)
+        .into_par_iter()
+        .map(|i| FheInt16::encrypt(i, pks))
+        .collect()
+}
+
+/// Build the encrypted negafibonacci table with parallelization, aligned with
+/// [`build_encrypted_signed_indices`].
+pub fn build_encrypted_negafibs(pks: &PublicKey) -> Vec<FheInt16> {
+    build_negafibonacci_table_plain()
+        .par_iter()
+        .copied()
+        .map(|v| FheInt16::encrypt(v, pks))
+        .collect()
+}
+
+/// Lookup of `F(n)` for a signed encrypted `n` over the negafibonacci tables,
+/// with equality + select.
+///
+/// Indices outside the table fall back to `F(0) = 0`, like the unsigned lookup.
+/// Requires the server key to be set on the calling thread.
+pub fn negafibonacci_lookup_with_tables(
+    n: &FheInt16,
+    encrypted_indices: &[FheInt16],
+    encrypted_values: &[FheInt16],
+) -> FheInt16 {
+    let mut result = FheInt16::encrypt_trivial(0i16);
+    for (i, value) in encrypted_values.iter().enumerate() {
+        let is_match = index_matches(n, encrypted_indices, i);
+        result = ops::select(&is_match, value, &result);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestHarness;
+
+    #[test]
+    fn test_negafibonacci_plaintext() {
+        let table = build_negafibonacci_table_plain();
+        let zero = usize::try_from(MAX_NEGAFIBONACCI_INDEX).unwrap();
+        assert_eq!(table[zero - 7..=zero + 1], [13, -8, 5, -3, 2, -1, 1, 0, 1]);
+        assert_eq!(negafibonacci_plaintext(-MAX_NEGAFIBONACCI_INDEX), -144);
+        assert_eq!(negafibonacci_plaintext(MAX_NEGAFIBONACCI_INDEX), 144);
+    }
+
+    #[test]
+    #[should_panic(expected = "|n| is at most 12")]
+    fn test_negafibonacci_plaintext_out_of_range() {
+        negafibonacci_plaintext(MAX_NEGAFIBONACCI_INDEX + 1);
+    }
+
+    #[test]
+    fn test_negafibonacci_lookup() {
+        let harness = TestHarness::new();
+        let encrypted_indices = build_encrypted_signed_indices(harness.pks());
+        let encrypted_negafibs = build_encrypted_negafibs(harness.pks());
+        harness.run(|| {
+            for (n, expected) in [(-6i16, -8i16), (-7, 13), (0, 0), (10, 55), (-13, 0), (13, 0)] {
+                let encrypted = FheInt16::encrypt(n, harness.client_key());
+                let enc_lt = negafibonacci_lookup_with_tables(&encrypted, &encrypted_indices, &encrypted_negafibs);
+                let dec_lt: i16 = harness.decrypt(&enc_lt);
+
+                assert_eq!(
+                    dec_lt, expected,
+                    "Negafibonacci lookup mismatch for n = {}: encrypted = {}, expected = {}",
+                    n, dec_lt, expected
+                );
+            }
+        });
+    }
+}
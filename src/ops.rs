@@ -0,0 +1,168 @@
+//! The homomorphic operations of the strategies, counted by
+//! [`stats`](crate::stats) with the `stats` feature.
+//!
+//! Without the feature these are plain calls to the tfhe operators.
+//! Comparisons, additions, subtractions, multiplications, divisions and selects
+//! go through here, with a ciphertext or a scalar as the other operand. Not
+//! counted: bitwise operations, casts, `min` and `%`.
+
+use crate::FheBool;
+use std::ops::{Add, Mul, Sub};
+use tfhe::prelude::*;
+
+pub(crate) fn eq<T>(a: &T, b: &T) -> FheBool
+where
+    T: for<'a> FheEq<&'a T>,
+{
+    #[cfg(feature = "stats")]
+    crate::stats::count(|counts| counts.eq += 1);
+    a.eq(b)
+}
+
+/// Counted with the `eq` between two ciphertexts, which it replaces.
+pub(crate) fn scalar_eq<T, S>(a: &T, b: S) -> FheBool
+where
+    T: FheEq<S>,
+{
+    #[cfg(feature = "stats")]
+    crate::stats::count(|counts| counts.eq += 1);
+    a.eq(b)
+}
+
+/// Counted with the `eq`, like [`scalar_eq`].
+pub(crate) fn ne<T, S>(a: &T, b: S) -> FheBool
+where
+    T: FheEq<S>,
+{
+    #[cfg(feature = "stats")]
+    crate::stats::count(|counts| counts.eq += 1);
+    a.ne(b)
+}
+
+/// Ordering comparisons are counted with the `eq`.
+pub(crate) fn gt<T, B>(a: &T, b: B) -> FheBool
+where
+    T: FheOrd<B>,
+{
+    #[cfg(feature = "stats")]
+    crate::stats::count(|counts| counts.eq += 1);
+    a.gt(b)
+}
+
+/// Counted with the `eq`, see [`gt`].
+pub(crate) fn ge<T, B>(a: &T, b: B) -> FheBool
+where
+    T: FheOrd<B>,
+{
+    #[cfg(feature = "stats")]
+    crate::stats::count(|counts| counts.eq += 1);
+    a.ge(b)
+}
+
+/// Counted with the `eq`, see [`gt`].
+pub(crate) fn le<T, B>(a: &T, b: B) -> FheBool
+where
+    T: FheOrd<B>,
+{
+    #[cfg(feature = "stats")]
+    crate::stats::count(|counts| counts.eq += 1);
+    a.le(b)
+}
+
+/// Counted with the `eq`, see [`gt`].
+pub(crate) fn lt<T, B>(a: &T, b: B) -> FheBool
+where
+    T: FheOrd<B>,
+{
+    #[cfg(feature = "stats")]
+    crate::stats::count(|counts| counts.eq += 1);
+    a.lt(b)
+}
+
+pub(crate) fn select<T>(condition: &FheBool, if_true: &T, if_false: &T) -> T
+where
+    FheBool: IfThenElse<T>,
+{
+    #[cfg(feature = "stats")]
+    crate::stats::count(|counts| counts.select += 1);
+    condition.select(if_true, if_false)
+}
+
+pub(crate) fn add<T>(a: &T, b: &T) -> T
+where
+    for<'a> &'a T: Add<&'a T, Output = T>,
+{
+    #[cfg(feature = "stats")]
+    crate::stats::count(|counts| counts.add += 1);
+    a + b
+}
+
+/// Counted with the additions, the overflow flag comes with the sum.
+pub(crate) fn overflowing_add<T>(a: &T, b: &T) -> (T, FheBool)
+where
+    for<'a> &'a T: OverflowingAdd<&'a T, Output = T>,
+{
+    #[cfg(feature = "stats")]
+    crate::stats::count(|counts| counts.add += 1);
+    a.overflowing_add(b)
+}
+
+/// Counted with the additions.
+pub(crate) fn sub<T>(a: &T, b: &T) -> T
+where
+    for<'a> &'a T: Sub<&'a T, Output = T>,
+{
+    #[cfg(feature = "stats")]
+    crate::stats::count(|counts| counts.add += 1);
+    a - b
+}
+
+/// Counted with the additions, like [`add`].
+pub(crate) fn scalar_add<T, S>(a: &T, b: S) -> T
+where
+    for<'a> &'a T: Add<S, Output = T>,
+{
+    #[cfg(feature = "stats")]
+    crate::stats::count(|counts| counts.add += 1);
+    a + b
+}
+
+/// Counted with the additions, like [`sub`].
+pub(crate) fn scalar_sub<T, S>(a: &T, b: S) -> T
+where
+    for<'a> &'a T: Sub<S, Output = T>,
+{
+    #[cfg(feature = "stats")]
+    crate::stats::count(|counts| counts.add += 1);
+    a - b
+}
+
+pub(crate) fn mul<T>(a: &T, b: &T) -> T
+where
+    for<'a> &'a T: Mul<&'a T, Output = T>,
+{
+    #[cfg(feature = "stats")]
+    crate::stats::count(|counts| counts.mul += 1);
+    a * b
+}
+
+/// Counted with the multiplications, like [`mul`].
+pub(crate) fn scalar_mul<T, S>(a: &T, b: S) -> T
+where
+    for<'a> &'a T: Mul<S, Output = T>,
+{
+    #[cfg(feature = "stats")]
+    crate::stats::count(|counts| counts.mul += 1);
+    a * b
+}
+
+/// Counted with the multiplications: tfhe divides by a scalar through a
+/// multiplication by its precomputed inverse.
+pub(crate) fn scalar_div_rem<T, S>(a: &T, b: S) -> (T, T)
+where
+    for<'a> &'a T: DivRem<S, Output = (T, T)>,
+{
+    #[cfg(feature = "stats")]
+    crate::stats::count(|counts| counts.mul += 1);
+    a.div_rem(b)
+}
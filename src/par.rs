@@ -0,0 +1,45 @@
+//! Parallel iteration with rayon, or sequential stand-ins without the `parallel`
+//! feature.
+//!
+//! Call sites import `crate::par::*` and keep using `into_par_iter`, `par_iter`
+//! and `reduce_with`; with the feature disabled these resolve to plain
+//! iterators running on the calling thread.
+
+#[cfg(feature = "parallel")]
+pub(crate) use rayon::prelude::*;
+
+#[cfg(not(feature = "parallel"))]
+pub(crate) use sequential::*;
+
+#[cfg(not(feature = "parallel"))]
+mod sequential {
+    pub(crate) trait IntoParallelIterator: IntoIterator + Sized {
+        fn into_par_iter(self) -> Self::IntoIter {
+            self.into_iter()
+        }
+    }
+
+    impl<I: IntoIterator> IntoParallelIterator for I {}
+
+    pub(crate) trait IntoParallelRefIterator<'a> {
+        type Iter: Iterator;
+
+        fn par_iter(&'a self) -> Self::Iter;
+    }
+
+    impl<'a, T: 'a> IntoParallelRefIterator<'a> for [T] {
+        type Iter = std::slice::Iter<'a, T>;
+
+        fn par_iter(&'a self) -> Self::Iter {
+            self.iter()
+        }
+    }
+
+    pub(crate) trait ParallelIterator: Iterator + Sized {
+        fn reduce_with(self, op: impl Fn(Self::Item, Self::Item) -> Self::Item) -> Option<Self::Item> {
+            self.reduce(op)
+        }
+    }
+
+    impl<I: Iterator> ParallelIterator for I {}
+}
@@ -0,0 +1,140 @@
+//! `F(n) mod m` for any encrypted 16-bit `n`, through the Pisano period.
+//!
+//! Modulo `m`, the Fibonacci sequence is periodic: its period `π(m)` is the
+//! Pisano period, e.g. `π(10) = 60`. With a plaintext `m`, the server reduces
+//! the encrypted `n` modulo `π(m)` and looks the result up in a table of one
+//! period, so `n` is not limited to [`MAX_FIBONACCI_INDEX`](crate::MAX_FIBONACCI_INDEX)
+//! and no term overflows.
+
+use crate::ops;
+use crate::par::*;
+use crate::{FheUint16, PublicKey};
+use tfhe::prelude::*;
+
+/// Pisano period `π(m)`: the period of `F(n) mod m`, at most `6m`.
+///
+/// # Panics
+///
+/// If `m` is 0.
+pub fn pisano_period(m: u16) -> u32 {
+    assert!(m > 0, "the modulus must be positive");
+    let m = u32::from(m);
+    let (mut a, mut b) = (0, 1 % m);
+    let mut period = 0;
+    loop {
+        // invariant: a = F(period) mod m, b = F(period + 1) mod m
+        (a, b) = (b, (a + b) % m);
+        period += 1;
+        if (a, b) == (0, 1 % m) {
+            return period;
+        }
+    }
+}
+
+/// One period of the sequence modulo `m`: `F(0) mod m, ..., F(π(m) - 1) mod m`.
+///
+/// # Panics
+///
+/// If `m` is 0.
+pub fn fibonacci_mod_table(m: u16) -> Vec<u16> {
+    let period = pisano_period(m) as usize;
+    let m = u32::from(m);
+    let mut table = Vec::with_capacity(period);
+    let (mut a, mut b) = (0, 1 % m);
+    for _ in 0..period {
+        table.push(a as u16);
+        (a, b) = (b, (a + b) % m);
+    }
+    table
+}
+
+/// Build the encrypted table of [`fibonacci_mod_table`] with parallelization.
+///
+/// # Panics
+///
+/// If `m` is 0.
+pub fn build_encrypted_period_table(m: u16, pks: &PublicKey) -> Vec<FheUint16> {
+    fibonacci_mod_table(m)
+        .par_iter()
+        .map(|v| FheUint16::encrypt(*v, pks))
+        .collect()
+}
+
+/// `F(n) mod m` for an encrypted `n`, over the table built by
+/// [`build_encrypted_period_table`] for the same `m`.
+///
+/// `n` is reduced with one scalar remainder by `π(m)`, then every entry of the
+/// period is compared against the reduced index as a plaintext scalar and
+/// selected: the cost grows with `π(m)`, up to `6m` comparisons and selects.
+/// A period longer than `u16::MAX` is only scanned up to index `u16::MAX`, the
+/// last one `n` can reach.
+/// Requires the server key to be set on the calling thread.
+///
+/// # Panics
+///
+/// If `m` is 0 or `period_table` does not hold exactly `π(m)` entries.
+pub fn fibonacci_mod_periodic(n: &FheUint16, m: u16, period_table: &[FheUint16]) -> FheUint16 {
+    let period = pisano_period(m);
+    assert_eq!(period_table.len() as u32, period, "the period table of m = {} has π(m) entries", m);
+
+    // Past u16::MAX, the period is longer than any index: n is already reduced.
+    let reduced = match u16::try_from(period) {
+        Ok(period) => ops::scalar_div_rem(n, period).1,
+        Err(_) => n.clone(),
+    };
+    let mut result = period_table[0].clone();
+    for (i, value) in reachable_entries(period_table).skip(1) {
+        let is_match = ops::scalar_eq(&reduced, i);
+        result = ops::select(&is_match, value, &result);
+    }
+    result
+}
+
+/// The entries of `period_table` with their index, up to the last one a 16-bit
+/// index can reach: past `u16::MAX`, the rest of a longer period is never looked
+/// up.
+fn reachable_entries<T>(period_table: &[T]) -> impl Iterator<Item = (u16, &T)> {
+    (0..=u16::MAX).zip(period_table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestHarness;
+
+    #[test]
+    fn test_pisano_period() {
+        for (m, period) in [(1, 1), (2, 3), (3, 8), (5, 20), (10, 60), (100, 300)] {
+            assert_eq!(pisano_period(m), period, "π({})", m);
+        }
+        let table = fibonacci_mod_table(10);
+        assert_eq!(table.len(), 60);
+        assert_eq!(table[..8], [0, 1, 1, 2, 3, 5, 8, 3]);
+    }
+
+    #[test]
+    fn test_reachable_entries_of_a_long_period() {
+        // π(31250) = lcm(π(2), π(5^6)) = lcm(3, 62500): longer than any u16 index.
+        let table = fibonacci_mod_table(31250);
+        assert_eq!(table.len(), 187500);
+
+        let entries: Vec<_> = reachable_entries(&table).collect();
+        assert_eq!(entries.len(), usize::from(u16::MAX) + 1);
+        assert_eq!(entries.last(), Some(&(u16::MAX, &table[usize::from(u16::MAX)])));
+    }
+
+    #[test]
+    fn test_fibonacci_mod_periodic() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            let period_table = build_encrypted_period_table(10, harness.pks());
+
+            // F(100) = 354224848179261915075, and 100 mod 60 = 40: F(40) = 102334155.
+            for (n, expected) in [(100u16, 5u16), (7, 3), (60, 0)] {
+                let encrypted = harness.encrypt(n);
+                let dec: u16 = harness.decrypt(&fibonacci_mod_periodic(&encrypted, 10, &period_table));
+                assert_eq!(dec, expected, "F({}) mod 10", n);
+            }
+        });
+    }
+}
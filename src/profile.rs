@@ -0,0 +1,79 @@
+//! Per-iteration timings of the additions loop, with the `profile` feature.
+//!
+//! Each of the `MAX_FIBONACCI_INDEX - 1` iterations of
+//! [`fibonacci_additions`](crate::fibonacci_additions) is one `add`, one `eq`
+//! and one `select` on 16-bit ciphertexts, whose cost does not depend on the
+//! values encrypted: the durations should stay flat as `F(k)` grows.
+
+use crate::{fibonacci_additions_with, FheUint16, PublicKey, MAX_FIBONACCI_INDEX};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// [`fibonacci_additions`](crate::fibonacci_additions), also returning the
+/// wall-clock time of each iteration, in order.
+///
+/// Building the index table and setting up the loop are not part of any
+/// iteration. Requires the server key to be set on the calling thread.
+pub fn fibonacci_additions_profiled(n: &FheUint16, pks: &PublicKey) -> (FheUint16, Vec<Duration>) {
+    let mut marks = Vec::with_capacity(usize::from(MAX_FIBONACCI_INDEX) + 1);
+    let result = fibonacci_additions_with(n, pks, |_| marks.push(Instant::now()));
+    // F(0) and F(1) are both passed before the loop starts.
+    let iterations = marks[1..].windows(2).map(|pair| pair[1] - pair[0]).collect();
+    (result, iterations)
+}
+
+/// Minimum, maximum and mean of a set of durations.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Summary {
+    pub min: Duration,
+    pub max: Duration,
+    pub mean: Duration,
+}
+
+impl Summary {
+    /// Summarize `durations`; `None` when there are none.
+    pub fn of(durations: &[Duration]) -> Option<Self> {
+        let count = u32::try_from(durations.len()).ok().filter(|count| *count > 0)?;
+        Some(Self {
+            min: *durations.iter().min()?,
+            max: *durations.iter().max()?,
+            mean: durations.iter().sum::<Duration>() / count,
+        })
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "min {:?}, max {:?}, mean {:?}", self.min, self.max, self.mean)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fibonacci_plaintext;
+    use crate::test_support::TestHarness;
+
+    #[test]
+    fn test_fibonacci_additions_profiled() {
+        let harness = TestHarness::new();
+        let (result, iterations) =
+            harness.run(|| fibonacci_additions_profiled(&harness.encrypt(11), harness.pks()));
+
+        let decrypted: u16 = harness.decrypt(&result);
+        assert_eq!(decrypted, fibonacci_plaintext(11));
+        assert_eq!(iterations.len(), 23);
+        assert!(iterations.iter().all(|duration| *duration > Duration::ZERO));
+    }
+
+    #[test]
+    fn test_summary() {
+        let durations = [3, 1, 2].map(Duration::from_millis);
+        let summary = Summary::of(&durations).unwrap();
+        assert_eq!(
+            summary,
+            Summary { min: Duration::from_millis(1), max: Duration::from_millis(3), mean: Duration::from_millis(2) }
+        );
+        assert_eq!(Summary::of(&[]), None);
+    }
+}
@@ -0,0 +1,126 @@
+//! Tables of arbitrary integer linear recurrences.
+//!
+//! A recurrence `a(n) = coeffs[0]*a(n-1) + ... + coeffs[k-1]*a(n-k)` is given by
+//! its coefficients and its `k` initial terms `a(0), ..., a(k-1)`; e.g.
+//! Fibonacci is `coeffs = [1, 1]`, `initial = [0, 1]` and Pell is
+//! `coeffs = [2, 1]`, `initial = [0, 1]`. Lookup reuses the encrypted index
+//! table of [`build_encrypted_indices`](crate::build_encrypted_indices), so
+//! `max_index` is at most [`MAX_FIBONACCI_INDEX`].
+
+use crate::sequence::lookup_up_to;
+use crate::{encrypt_with_progress, FheUint16, PublicKey, MAX_FIBONACCI_INDEX};
+
+/// Build the plaintext table `a(0), ..., a(max_index)` of a linear recurrence.
+///
+/// Terms are computed with wrapping `u16` arithmetic; callers pick `max_index`
+/// so that the table does not overflow.
+///
+/// # Panics
+///
+/// If `initial` is empty or `coeffs` and `initial` have different lengths.
+pub fn build_linear_recurrence_table(coeffs: &[u16], initial: &[u16], max_index: u16) -> Vec<u16> {
+    assert!(!initial.is_empty(), "a recurrence needs at least one initial term");
+    assert_eq!(coeffs.len(), initial.len(), "one initial term per coefficient");
+
+    let len = usize::from(max_index) + 1;
+    let mut table = initial.to_vec();
+    while table.len() < len {
+        let next = coeffs
+            .iter()
+            .zip(table.iter().rev())
+            .fold(0u16, |acc, (c, a)| acc.wrapping_add(c.wrapping_mul(*a)));
+        table.push(next);
+    }
+    table.truncate(len);
+    table
+}
+
+/// Build the encrypted table of a linear recurrence with parallelization.
+///
+/// Entry `i` is an encryption of `a(i)`, aligned with
+/// [`build_encrypted_indices`](crate::build_encrypted_indices).
+///
+/// # Panics
+///
+/// Like [`build_linear_recurrence_table`], or if `max_index > MAX_FIBONACCI_INDEX`.
+pub fn build_encrypted_linear_recurrence(
+    coeffs: &[u16],
+    initial: &[u16],
+    max_index: u16,
+    pks: &PublicKey,
+) -> Vec<FheUint16> {
+    assert!(max_index <= MAX_FIBONACCI_INDEX, "index table stops at {}", MAX_FIBONACCI_INDEX);
+    encrypt_with_progress(build_linear_recurrence_table(coeffs, initial, max_index), pks, |_, _| {})
+}
+
+/// Lookup of `a(n)` over a table built by [`build_encrypted_linear_recurrence`],
+/// with equality + select.
+///
+/// Requires the server key to be set on the calling thread.
+///
+/// # Panics
+///
+/// If `encrypted_values` is empty or longer than `encrypted_indices`.
+pub fn linear_recurrence_lookup(
+    n: &FheUint16,
+    encrypted_indices: &[FheUint16],
+    encrypted_values: &[FheUint16],
+) -> FheUint16 {
+    assert!(!encrypted_values.is_empty(), "the table of values is empty");
+    assert!(encrypted_values.len() <= encrypted_indices.len(), "index table is too short");
+    lookup_up_to(n, encrypted_indices, encrypted_values, encrypted_values.len() - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build_fibonacci_table_plain;
+    use crate::sequence::{build_table_plain, Pell, Sequence, Tribonacci};
+    use crate::test_support::TestHarness;
+
+    #[test]
+    fn test_linear_recurrence_table() {
+        assert_eq!(
+            build_linear_recurrence_table(&[1, 1], &[0, 1], MAX_FIBONACCI_INDEX),
+            build_fibonacci_table_plain()
+        );
+        assert_eq!(build_linear_recurrence_table(&[2, 1], &[0, 1], Pell::MAX_INDEX), build_table_plain::<Pell>());
+        assert_eq!(build_linear_recurrence_table(&[1, 1], &[0, 1], 0), [0]);
+
+        let tribonacci = build_linear_recurrence_table(&[1, 1, 1], &[0, 0, 1], Tribonacci::MAX_INDEX);
+        assert_eq!(tribonacci[..10], [0, 0, 1, 1, 2, 4, 7, 13, 24, 44]);
+        assert_eq!(tribonacci[usize::from(Tribonacci::MAX_INDEX)], 35890);
+    }
+
+    #[test]
+    fn test_tribonacci_lookup() {
+        let harness = TestHarness::new();
+        let coeffs = [1, 1, 1];
+        let initial = [0, 0, 1];
+        let tribonacci = build_linear_recurrence_table(&coeffs, &initial, Tribonacci::MAX_INDEX);
+        let encrypted_tribonacci =
+            build_encrypted_linear_recurrence(&coeffs, &initial, Tribonacci::MAX_INDEX, harness.pks());
+        harness.run(|| {
+            for n in [0u16, 2, 9, Tribonacci::MAX_INDEX] {
+                let encrypted = harness.encrypt(n);
+                let enc_lt = linear_recurrence_lookup(&encrypted, harness.indices(), &encrypted_tribonacci);
+                let dec_lt: u16 = harness.decrypt(&enc_lt);
+
+                let expected = tribonacci[usize::from(n)];
+                assert_eq!(
+                    dec_lt, expected,
+                    "Tribonacci lookup mismatch for n = {}: encrypted = {}, plaintext = {}",
+                    n, dec_lt, expected
+                );
+            }
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "the table of values is empty")]
+    fn test_linear_recurrence_lookup_empty_table() {
+        // The check runs before any homomorphic operation: no server key needed.
+        let n = TestHarness::new().encrypt(0);
+        linear_recurrence_lookup(&n, &[], &[]);
+    }
+}
@@ -0,0 +1,306 @@
+//! Running the strategies for one index and returning what was measured.
+//!
+//! [`run`], [`run_all_strategies`] and [`run_strategies`] return the decrypted
+//! results, and the last two the durations, as values, so a library user or a
+//! test can assert on them instead of parsing the CLI output. The CLI itself
+//! goes through [`compute_strategies`] with the tables it built or loaded.
+
+use crate::config::FibConfig;
+use crate::keys::{generate_keys_with, Keys};
+#[cfg(feature = "stats")]
+use crate::{stats::record, OpCounts};
+use crate::{
+    build_encrypted_fibs, build_encrypted_indices, fibonacci_lookup_with_tables, fibonacci_matrix_pow,
+    fibonacci_plaintext, with_server_key, Cancelled, FheUint16, PublicKey, StrategyChoice,
+};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use tfhe::prelude::*;
+use tfhe::ClientKey;
+
+/// Strategies run by [`run_strategies`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Selection {
+    pub additions: bool,
+    pub lookup: bool,
+    pub matrix: bool,
+}
+
+impl Selection {
+    /// Every strategy, as run by [`run_all_strategies`].
+    pub const ALL: Self = Self { additions: true, lookup: true, matrix: true };
+}
+
+impl From<StrategyChoice> for Selection {
+    fn from(strategy: StrategyChoice) -> Self {
+        Self { additions: strategy.additions(), lookup: strategy.lookup(), matrix: strategy.matrix() }
+    }
+}
+
+/// Decrypted result of each strategy, next to the plaintext reference; `None`
+/// for strategies that did not run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Results {
+    pub expected: u16,
+    pub additions: Option<u16>,
+    pub lookup: Option<u16>,
+    pub matrix: Option<u16>,
+}
+
+/// Wall-clock time of each step of [`run_strategies`]; `None` for skipped steps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Timings {
+    /// Building the encrypted index and Fibonacci tables, only done for the
+    /// lookup, and never by [`compute_strategies`], which is given the tables.
+    pub setup: Option<Duration>,
+    pub additions: Option<Duration>,
+    /// The lookup alone, on the tables built during `setup`.
+    pub lookup: Option<Duration>,
+    pub matrix: Option<Duration>,
+}
+
+/// Homomorphic operations of each strategy run by [`compute_strategies`], with
+/// the `stats` feature; `None` for strategies that did not run.
+#[cfg(feature = "stats")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OpCountsByStrategy {
+    pub additions: Option<OpCounts>,
+    pub lookup: Option<OpCounts>,
+    pub matrix: Option<OpCounts>,
+}
+
+/// Encrypted result of each strategy run by [`compute_strategies`], before the
+/// client decrypts them.
+pub struct Ciphertexts {
+    pub additions: Option<FheUint16>,
+    pub lookup: Option<FheUint16>,
+    pub matrix: Option<FheUint16>,
+    /// Recorded strategy by strategy, so that the counts of one are not mixed
+    /// with those of the others.
+    #[cfg(feature = "stats")]
+    pub op_counts: OpCountsByStrategy,
+    /// Time of each iteration of the additions loop, with the `profile`
+    /// feature; `None` when the additions did not run.
+    #[cfg(feature = "profile")]
+    pub additions_iterations: Option<Vec<Duration>>,
+}
+
+impl Ciphertexts {
+    /// Decrypt every result, next to the plaintext reference for `n`.
+    pub fn decrypt(&self, n: u16, client_key: &ClientKey) -> Results {
+        let decrypt = |ct: &Option<FheUint16>| ct.as_ref().map(|ct| ct.decrypt(client_key));
+        Results {
+            expected: fibonacci_plaintext(n),
+            additions: decrypt(&self.additions),
+            lookup: decrypt(&self.lookup),
+            matrix: decrypt(&self.matrix),
+        }
+    }
+}
+
+/// The default flow of the CLI for one index, end to end: generate fresh keys
+/// with the default parameters, encrypt `clear_a`, run the strategies selected
+/// by default (all of them) through [`compute_strategies`], like the CLI, and
+/// decrypt their results.
+///
+/// Key generation dominates; reuse keys with [`run_all_strategies`] to run
+/// several indices.
+pub fn run(clear_a: u16) -> Results {
+    let keys = Keys::from(generate_keys_with(FibConfig::default()));
+    let (results, _) = run_strategies(clear_a, &keys, Selection::from(StrategyChoice::default()));
+    results
+}
+
+/// [`run_strategies`] with every strategy selected.
+pub fn run_all_strategies(n: u16, keys: &Keys) -> (Results, Timings) {
+    run_strategies(n, keys, Selection::ALL)
+}
+
+/// Encrypt `n`, compute `F(n)` with the strategies in `selection` and decrypt
+/// the results.
+///
+/// Skipped strategies cost nothing: skipping the lookup also skips building its
+/// tables. The server key is set with [`with_server_key`] for the duration of
+/// the call. Encryption and decryption are not part of the timings.
+pub fn run_strategies(n: u16, keys: &Keys, selection: Selection) -> (Results, Timings) {
+    let encrypted_n = FheUint16::encrypt(n, &keys.client_key);
+
+    let (ciphertexts, timings) = with_server_key(keys.server_key.clone(), || {
+        let tables = selection.lookup.then(|| {
+            let t_setup = Instant::now();
+            let encrypted_indices = build_encrypted_indices(&keys.public_key);
+            let encrypted_fibs = build_encrypted_fibs(&keys.public_key);
+            (encrypted_indices, encrypted_fibs, t_setup.elapsed())
+        });
+
+        let table_refs = tables.as_ref().map(|(indices, fibs, _)| (&indices[..], &fibs[..]));
+        let (ciphertexts, timings) = compute_strategies(&encrypted_n, &keys.public_key, table_refs, selection);
+        let setup = tables.as_ref().map(|(_, _, setup)| *setup);
+        (ciphertexts, Timings { setup, ..timings })
+    });
+
+    (ciphertexts.decrypt(n, &keys.client_key), timings)
+}
+
+/// Server-side half of [`run_strategies`]: compute `F(n)` for the encrypted `n`
+/// with the strategies in `selection`, the lookup running over `tables`, the
+/// index and Fibonacci tables built or loaded once by the caller.
+///
+/// `setup` is always `None` in the returned timings. With the `stats` feature,
+/// the operations of each strategy are recorded in `op_counts`, and with the
+/// `profile` feature the iterations of the additions are timed in
+/// `additions_iterations`. Requires the server key to be set on the calling
+/// thread.
+///
+/// # Panics
+///
+/// If the lookup is selected without `tables`.
+pub fn compute_strategies(
+    n: &FheUint16,
+    public_key: &PublicKey,
+    tables: Option<(&[FheUint16], &[FheUint16])>,
+    selection: Selection,
+) -> (Ciphertexts, Timings) {
+    let never_cancelled = AtomicBool::new(false);
+    let Ok(computed) = compute_strategies_cancellable(n, public_key, tables, selection, &never_cancelled) else {
+        unreachable!("nothing sets the flag")
+    };
+    computed
+}
+
+/// [`compute_strategies`], returning [`Cancelled`] once `cancelled` is set.
+///
+/// The flag is checked between the iterations of the additions and before
+/// each strategy starts, never within a homomorphic operation. With the
+/// `profile` feature, the profiled additions loop is only checked before it
+/// starts.
+///
+/// # Panics
+///
+/// If the lookup is selected without `tables`.
+pub fn compute_strategies_cancellable(
+    n: &FheUint16,
+    public_key: &PublicKey,
+    tables: Option<(&[FheUint16], &[FheUint16])>,
+    selection: Selection,
+    cancelled: &AtomicBool,
+) -> Result<(Ciphertexts, Timings), Cancelled> {
+    let timed = |run: bool, compute: &dyn Fn() -> Result<FheUint16, Cancelled>| {
+        run.then(|| {
+            if cancelled.load(Ordering::Relaxed) {
+                return Err(Cancelled);
+            }
+            let t_start = Instant::now();
+            let (ct, op_counts) = record(compute);
+            Ok((ct?, t_start.elapsed(), op_counts))
+        })
+        .transpose()
+    };
+
+    #[cfg(feature = "profile")]
+    let additions_iterations = std::cell::Cell::new(None);
+    let additions = timed(selection.additions, &|| {
+        #[cfg(feature = "profile")]
+        {
+            let (ct, iterations) = crate::profile::fibonacci_additions_profiled(n, public_key);
+            additions_iterations.set(Some(iterations));
+            Ok(ct)
+        }
+        #[cfg(not(feature = "profile"))]
+        crate::fibonacci_additions_cancellable(n, public_key, cancelled)
+    })?;
+    let lookup = timed(selection.lookup, &|| {
+        let (encrypted_indices, encrypted_fibs) = tables.expect("the lookup needs tables");
+        Ok(fibonacci_lookup_with_tables(n, encrypted_indices, encrypted_fibs))
+    })?;
+    let matrix = timed(selection.matrix, &|| Ok(fibonacci_matrix_pow(n, public_key)))?;
+
+    let timings = Timings {
+        setup: None,
+        additions: additions.as_ref().map(|(_, duration, _)| *duration),
+        lookup: lookup.as_ref().map(|(_, duration, _)| *duration),
+        matrix: matrix.as_ref().map(|(_, duration, _)| *duration),
+    };
+    #[cfg(feature = "stats")]
+    let op_counts = OpCountsByStrategy {
+        additions: additions.as_ref().map(|(_, _, op_counts)| *op_counts),
+        lookup: lookup.as_ref().map(|(_, _, op_counts)| *op_counts),
+        matrix: matrix.as_ref().map(|(_, _, op_counts)| *op_counts),
+    };
+    let ciphertexts = Ciphertexts {
+        additions: additions.map(|(ct, _, _)| ct),
+        lookup: lookup.map(|(ct, _, _)| ct),
+        matrix: matrix.map(|(ct, _, _)| ct),
+        #[cfg(feature = "stats")]
+        op_counts,
+        #[cfg(feature = "profile")]
+        additions_iterations: additions_iterations.into_inner(),
+    };
+    Ok((ciphertexts, timings))
+}
+
+/// [`stats::record`](crate::stats::record) without the `stats` feature: nothing
+/// is counted.
+#[cfg(not(feature = "stats"))]
+fn record<T>(f: impl FnOnce() -> T) -> (T, ()) {
+    (f(), ())
+}
+
+/// Name of the faster strategy in `timings`, `"additions"` or `"lookup"`.
+///
+/// The lookup is compared without `setup`: its tables are built once and shared
+/// by every later query. A strategy that did not run is never recommended; the
+/// lookup is the default when neither did.
+pub fn strategy_recommendation(timings: &Timings) -> &'static str {
+    match (timings.additions, timings.lookup) {
+        (Some(additions), Some(lookup)) if additions < lookup => "additions",
+        (Some(_), None) => "additions",
+        _ => "lookup",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestHarness;
+
+    #[test]
+    fn test_run_all_strategies() {
+        let (results, timings) = run_all_strategies(10, TestHarness::new().keys());
+
+        assert_eq!(results, Results { expected: 55, additions: Some(55), lookup: Some(55), matrix: Some(55) });
+        // Every step does homomorphic work; a zero duration means it was skipped.
+        assert!(timings.setup.unwrap() > Duration::ZERO);
+        assert!(timings.additions.unwrap() > Duration::ZERO);
+        assert!(timings.lookup.unwrap() > Duration::ZERO);
+        assert!(timings.matrix.unwrap() > Duration::ZERO);
+    }
+
+    #[test]
+    fn test_strategy_recommendation() {
+        let timings = |additions: Option<u64>, lookup: Option<u64>| Timings {
+            setup: Some(Duration::from_secs(60)),
+            additions: additions.map(Duration::from_millis),
+            lookup: lookup.map(Duration::from_millis),
+            matrix: None,
+        };
+
+        assert_eq!(strategy_recommendation(&timings(Some(900), Some(300))), "lookup");
+        assert_eq!(strategy_recommendation(&timings(Some(200), Some(300))), "additions");
+        assert_eq!(strategy_recommendation(&timings(Some(900), None)), "additions");
+        assert_eq!(strategy_recommendation(&timings(None, Some(300))), "lookup");
+    }
+
+    #[test]
+    fn test_run_strategies_skips() {
+        let keys = TestHarness::new().keys();
+
+        let (results, timings) = run_strategies(7, keys, Selection { additions: false, lookup: true, matrix: false });
+        assert_eq!(results, Results { expected: 13, additions: None, lookup: Some(13), matrix: None });
+        assert!(timings.additions.is_none() && timings.matrix.is_none());
+
+        let (results, timings) = run_strategies(7, keys, Selection { additions: true, lookup: false, matrix: false });
+        assert_eq!(results, Results { expected: 13, additions: Some(13), lookup: None, matrix: None });
+        assert!(timings.setup.is_none() && timings.lookup.is_none());
+    }
+}
@@ -0,0 +1,96 @@
+//! Fibonacci lookup past [`MAX_FIBONACCI_INDEX`] with a two-level table.
+//!
+//! `F(n)` no longer fits `u16` past `n = 24`, so the table stores it wrapped
+//! modulo `2^16`, as `wrapping_add` does in
+//! [`build_fibonacci_table_plain`](crate::build_fibonacci_table_plain) and
+//! [`fibonacci_plaintext`]. The values are split in a low segment,
+//! `F(0..=24)`, and a high segment, `F(25..=46)`, both indexed by the standard
+//! index table `0..=24`: the lookup first selects the segment of `n`, then its
+//! entry within it, so the plain lookup's index table is reused as is.
+
+use crate::par::*;
+use crate::{fibonacci_plaintext, index_matches, ops, FheUint16, PublicKey, MAX_FIBONACCI_INDEX};
+use tfhe::prelude::*;
+
+/// Largest index covered by the segmented table, with wrapped values.
+pub const MAX_SEGMENTED_INDEX: u16 = 46;
+
+/// Number of entries of the low segment, and first index of the high one.
+pub const SEGMENT_LEN: u16 = MAX_FIBONACCI_INDEX + 1;
+
+/// `F(0), ..., F(MAX_SEGMENTED_INDEX)` wrapped modulo `2^16`.
+pub fn build_segmented_table_plain() -> Vec<u16> {
+    (0..=MAX_SEGMENTED_INDEX).map(fibonacci_plaintext).collect()
+}
+
+/// Build the low and high encrypted segments with parallelization: `F(0..=24)`
+/// and the wrapped `F(25..=46)`, entry `i` of each aligned with entry `i` of
+/// [`build_encrypted_indices`](crate::build_encrypted_indices).
+pub fn build_encrypted_segments(pks: &PublicKey) -> (Vec<FheUint16>, Vec<FheUint16>) {
+    let mut low: Vec<FheUint16> = build_segmented_table_plain()
+        .par_iter()
+        .map(|v| FheUint16::encrypt(*v, pks))
+        .collect();
+    let high = low.split_off(usize::from(SEGMENT_LEN));
+    (low, high)
+}
+
+/// `F(n)` wrapped modulo `2^16` for an encrypted `n` in `0..=MAX_SEGMENTED_INDEX`,
+/// over the [`build_encrypted_indices`](crate::build_encrypted_indices) table and
+/// the segments of [`build_encrypted_segments`].
+///
+/// A scalar comparison picks the segment and shifts `n` into it, then every
+/// entry of both segments is selected, whatever `n` is, and the segment of `n`
+/// wins. An index past `MAX_SEGMENTED_INDEX` falls through to `0`. Requires the
+/// server key to be set on the calling thread.
+pub fn fibonacci_lookup_segmented(
+    n: &FheUint16,
+    encrypted_indices: &[FheUint16],
+    low: &[FheUint16],
+    high: &[FheUint16],
+) -> FheUint16 {
+    let in_high = ops::ge(n, SEGMENT_LEN);
+    let local = ops::select(&in_high, &ops::scalar_sub(n, SEGMENT_LEN), n);
+
+    let mut low_result = FheUint16::encrypt_trivial(0u16);
+    let mut high_result = FheUint16::encrypt_trivial(0u16);
+    for (i, fib) in low.iter().enumerate() {
+        let is_match = index_matches(&local, encrypted_indices, i);
+        low_result = ops::select(&is_match, fib, &low_result);
+        if let Some(fib) = high.get(i) {
+            high_result = ops::select(&is_match, fib, &high_result);
+        }
+    }
+    ops::select(&in_high, &high_result, &low_result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestHarness;
+
+    #[test]
+    fn test_segmented_table_plain() {
+        let table = build_segmented_table_plain();
+        assert_eq!(table.len(), usize::from(MAX_SEGMENTED_INDEX) + 1);
+        assert_eq!(table[..usize::from(SEGMENT_LEN)], crate::build_fibonacci_table_plain());
+        // F(25) = 75025 and F(46) = 1836311903, modulo 2^16.
+        assert_eq!(table[25], 9489);
+        assert_eq!(u32::from(table[46]), 1_836_311_903 % (1 << 16));
+    }
+
+    #[test]
+    fn test_fibonacci_lookup_segmented() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            let (low, high) = build_encrypted_segments(harness.pks());
+            assert_eq!(high.len(), usize::from(MAX_SEGMENTED_INDEX - SEGMENT_LEN) + 1);
+
+            for n in [0u16, 7, MAX_FIBONACCI_INDEX, SEGMENT_LEN, 33, MAX_SEGMENTED_INDEX] {
+                let encrypted = harness.encrypt(n);
+                let dec: u16 = harness.decrypt(&fibonacci_lookup_segmented(&encrypted, harness.indices(), &low, &high));
+                assert_eq!(dec, fibonacci_plaintext(n), "segmented lookup mismatch for n = {}", n);
+            }
+        });
+    }
+}
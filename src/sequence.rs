@@ -0,0 +1,255 @@
+//! Integer sequences sharing the encrypted-table lookup.
+//!
+//! A sequence only needs its plaintext definition and the largest index whose
+//! value fits in `u16`; table construction and lookup are generic over
+//! [`Sequence`].
+
+use crate::ops;
+use crate::par::*;
+use crate::recurrence::build_linear_recurrence_table;
+use crate::{
+    encrypt_with_progress, fibonacci_plaintext, index_matches, lucas_plaintext, FheBool, FheUint16, FheUint8, PublicKey,
+};
+use tfhe::prelude::*;
+
+/// An integer sequence computed over `FheUint16`.
+pub trait Sequence {
+    /// Name used to select the sequence on the command line.
+    const NAME: &'static str;
+    /// Largest index whose value fits in `u16`.
+    const MAX_INDEX: u16;
+
+    /// Plaintext value at index `n`, for `n <= MAX_INDEX`.
+    fn plaintext(n: u16) -> u16;
+}
+
+/// `F(0) = 0`, `F(1) = 1`, `F(n) = F(n-1) + F(n-2)`.
+pub struct Fibonacci;
+
+impl Sequence for Fibonacci {
+    const NAME: &'static str = "fib";
+    const MAX_INDEX: u16 = crate::MAX_FIBONACCI_INDEX;
+
+    fn plaintext(n: u16) -> u16 {
+        fibonacci_plaintext(n)
+    }
+}
+
+/// `L(0) = 2`, `L(1) = 1`, `L(n) = L(n-1) + L(n-2)`.
+pub struct Lucas;
+
+impl Sequence for Lucas {
+    const NAME: &'static str = "lucas";
+    const MAX_INDEX: u16 = crate::MAX_LUCAS_INDEX;
+
+    fn plaintext(n: u16) -> u16 {
+        lucas_plaintext(n)
+    }
+}
+
+/// `P(0) = 0`, `P(1) = 1`, `P(n) = 2*P(n-1) + P(n-2)`; `P(14) = 80782` > `u16::MAX`.
+pub struct Pell;
+
+impl Sequence for Pell {
+    const NAME: &'static str = "pell";
+    const MAX_INDEX: u16 = 13;
+
+    fn plaintext(n: u16) -> u16 {
+        let mut a: u16 = 0;
+        let mut b: u16 = 1;
+        for _ in 0..n {
+            // b runs one term ahead and wraps once n = MAX_INDEX.
+            let tmp = b.wrapping_mul(2).wrapping_add(a);
+            a = b;
+            b = tmp;
+        }
+        a
+    }
+}
+
+/// `T(0) = 0`, `T(1) = 0`, `T(2) = 1`, `T(n) = T(n-1) + T(n-2) + T(n-3)`;
+/// `T(20) = 35890` and `T(21) = 66012` > `u16::MAX`.
+pub struct Tribonacci;
+
+impl Sequence for Tribonacci {
+    const NAME: &'static str = "tribonacci";
+    const MAX_INDEX: u16 = 20;
+
+    fn plaintext(n: u16) -> u16 {
+        tribonacci_plaintext(n)
+    }
+}
+
+/// Plaintext reference for `T(n)`, `n <= Tribonacci::MAX_INDEX`, as the last
+/// term of its [`build_linear_recurrence_table`].
+pub fn tribonacci_plaintext(n: u16) -> u16 {
+    let table = build_linear_recurrence_table(&[1, 1, 1], &[0, 0, 1], n);
+    table[usize::from(n)]
+}
+
+/// Largest index of `S` whose value fits in `u8`, e.g. 11 for tribonacci
+/// (`T(11) = 149`, `T(12) = 274`).
+pub fn max_index_u8<S: Sequence>() -> u16 {
+    (0..=S::MAX_INDEX)
+        .take_while(|&n| S::plaintext(n) <= u16::from(u8::MAX))
+        .last()
+        .expect("the first term fits in u8")
+}
+
+/// Build the plaintext table of `S` up to `S::MAX_INDEX`.
+pub fn build_table_plain<S: Sequence>() -> Vec<u16> {
+    (0..=S::MAX_INDEX).map(S::plaintext).collect()
+}
+
+/// Build the encrypted table of `S` with parallelization.
+///
+/// Entry `i` is an encryption of the `i`-th term, aligned with
+/// [`build_encrypted_indices`](crate::build_encrypted_indices).
+pub fn build_encrypted_table<S: Sequence>(pks: &PublicKey) -> Vec<FheUint16> {
+    build_encrypted_table_with_progress::<S>(pks, |_, _| {})
+}
+
+/// [`build_encrypted_table`], calling `progress(done, total)` each time a
+/// ciphertext has been encrypted.
+pub fn build_encrypted_table_with_progress<S: Sequence>(
+    pks: &PublicKey,
+    progress: impl Fn(usize, usize) + Sync,
+) -> Vec<FheUint16> {
+    encrypt_with_progress(build_table_plain::<S>(), pks, progress)
+}
+
+/// Build the table of `S` from trivial ciphertexts.
+///
+/// Trivial ciphertexts hold their value in the clear: this is insecure and only
+/// meant for testing the lookup logic. Requires the server key to be set on the
+/// calling thread.
+pub fn build_trivial_table<S: Sequence>() -> Vec<FheUint16> {
+    build_table_plain::<S>()
+        .into_iter()
+        .map(FheUint16::encrypt_trivial)
+        .collect()
+}
+
+/// Build the encrypted table of `S` up to [`max_index_u8`] as `FheUint8`, with
+/// parallelization.
+///
+/// Entries are half the width of [`build_encrypted_table`]'s, which makes the
+/// table, every select and the result cheaper; the index is still a
+/// `FheUint16`, aligned with
+/// [`build_encrypted_indices`](crate::build_encrypted_indices).
+pub fn build_encrypted_table_u8<S: Sequence>(pks: &PublicKey) -> Vec<FheUint8> {
+    (0..=max_index_u8::<S>())
+        .into_par_iter()
+        .map(|n| {
+            let value = u8::try_from(S::plaintext(n)).expect("max_index_u8 keeps values in u8");
+            FheUint8::encrypt(value, pks)
+        })
+        .collect()
+}
+
+/// [`lookup_with_tables`] over a table built by [`build_encrypted_table_u8`].
+///
+/// Only the first `max_index_u8::<S>() + 1` entries are read. Requires the
+/// server key to be set on the calling thread.
+pub fn lookup_with_tables_u8<S: Sequence>(
+    n: &FheUint16,
+    encrypted_indices: &[FheUint16],
+    encrypted_values: &[FheUint8],
+) -> FheUint8 {
+    lookup_up_to(n, encrypted_indices, encrypted_values, usize::from(max_index_u8::<S>()))
+}
+
+/// Lookup of the `n`-th term of `S` over an encrypted table, with equality +
+/// select.
+///
+/// `encrypted_indices` may be longer than the sequence (e.g. the Fibonacci index
+/// table); only its first `S::MAX_INDEX + 1` entries are read. Requires the
+/// server key to be set on the calling thread.
+pub fn lookup_with_tables<S: Sequence>(
+    n: &FheUint16,
+    encrypted_indices: &[FheUint16],
+    encrypted_values: &[FheUint16],
+) -> FheUint16 {
+    lookup_up_to(n, encrypted_indices, encrypted_values, usize::from(S::MAX_INDEX))
+}
+
+/// Equality + select over the first `max_index + 1` entries of both tables;
+/// `values[0]` when `n` matches none of them. Any tfhe integer types are
+/// accepted, e.g. `FheUint16` indices over a `FheUint8` table.
+///
+/// # Panics
+///
+/// If a table is shorter than `max_index + 1`.
+pub(crate) fn lookup_up_to<I, V>(n: &I, encrypted_indices: &[I], encrypted_values: &[V], max_index: usize) -> V
+where
+    I: for<'a> FheEq<&'a I>,
+    V: Clone,
+    FheBool: IfThenElse<V>,
+{
+    let values = encrypted_values.get(..=max_index).unwrap_or_else(|| {
+        panic!("index {} is past the end of a table of {} values", max_index, encrypted_values.len())
+    });
+    let mut result = values[0].clone();
+    for (i, value) in values.iter().enumerate().skip(1) {
+        let is_match = index_matches(n, encrypted_indices, i);
+        result = ops::select(&is_match, value, &result);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestHarness;
+    use crate::build_fibonacci_table_plain;
+
+    #[test]
+    fn test_plain_tables() {
+        assert_eq!(build_table_plain::<Fibonacci>(), build_fibonacci_table_plain());
+        assert_eq!(build_table_plain::<Lucas>()[..6], [2, 1, 3, 4, 7, 11]);
+        assert_eq!(Lucas::plaintext(Lucas::MAX_INDEX), 64079);
+        assert_eq!(build_table_plain::<Pell>()[..6], [0, 1, 2, 5, 12, 29]);
+        assert_eq!(Pell::plaintext(Pell::MAX_INDEX), 33461);
+        assert_eq!(build_table_plain::<Tribonacci>()[..10], [0, 0, 1, 1, 2, 4, 7, 13, 24, 44]);
+        assert_eq!(Tribonacci::plaintext(18), 10609);
+        assert_eq!(Tribonacci::plaintext(Tribonacci::MAX_INDEX), 35890);
+        assert_eq!(max_index_u8::<Tribonacci>(), 11);
+        assert_eq!(max_index_u8::<Pell>(), 7);
+    }
+
+    #[test]
+    fn test_pell_lookup() {
+        let harness = TestHarness::new();
+        let encrypted_pell = build_encrypted_table::<Pell>(harness.pks());
+        harness.run(|| {
+            for n in [0u16, 5, Pell::MAX_INDEX] {
+                let encrypted = harness.encrypt(n);
+                let enc_lt = lookup_with_tables::<Pell>(&encrypted, harness.indices(), &encrypted_pell);
+                let dec_lt: u16 = harness.decrypt(&enc_lt);
+
+                assert_eq!(dec_lt, Pell::plaintext(n), "Pell lookup mismatch for n = {}", n);
+            }
+        });
+    }
+
+    #[test]
+    fn test_tribonacci_lookup() {
+        let harness = TestHarness::new();
+        let encrypted_tribonacci = build_encrypted_table::<Tribonacci>(harness.pks());
+        let encrypted_tribonacci_u8 = build_encrypted_table_u8::<Tribonacci>(harness.pks());
+        harness.run(|| {
+            for n in [0u16, 2, 11, 16, Tribonacci::MAX_INDEX] {
+                let encrypted = harness.encrypt(n);
+                let enc_lt = lookup_with_tables::<Tribonacci>(&encrypted, harness.indices(), &encrypted_tribonacci);
+                let dec_lt: u16 = harness.decrypt(&enc_lt);
+                assert_eq!(dec_lt, tribonacci_plaintext(n), "Tribonacci lookup mismatch for n = {}", n);
+
+                if n <= max_index_u8::<Tribonacci>() {
+                    let enc_u8 = lookup_with_tables_u8::<Tribonacci>(&encrypted, harness.indices(), &encrypted_tribonacci_u8);
+                    let dec_u8: u8 = harness.decrypt(&enc_u8);
+                    assert_eq!(u16::from(dec_u8), tribonacci_plaintext(n), "8-bit tribonacci lookup mismatch for n = {}", n);
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,104 @@
+//! Versioned (de)serialization of keys and ciphertexts.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::io::{self, Read, Write};
+use tfhe::named::Named;
+use tfhe::safe_serialization::{safe_deserialize, safe_serialize, safe_serialized_size};
+use tfhe::{CompactCiphertextList, CompressedCiphertextList, FheUint16, Unversionize, Versionize};
+
+/// Upper bound on the serialized size of a single ciphertext.
+pub const CIPHERTEXT_SIZE_LIMIT: u64 = 1 << 30;
+
+/// Write a ciphertext with tfhe's safe serialization.
+pub fn write_ciphertext(ct: &FheUint16, writer: impl Write) -> io::Result<()> {
+    write_versioned(ct, writer, CIPHERTEXT_SIZE_LIMIT)
+}
+
+/// Read a ciphertext written by [`write_ciphertext`].
+pub fn read_ciphertext(reader: impl Read) -> io::Result<FheUint16> {
+    read_versioned(reader, CIPHERTEXT_SIZE_LIMIT)
+}
+
+/// Serialized size in bytes of a ciphertext, i.e. what it costs to send it over
+/// the network with [`write_ciphertext`]; any tfhe integer type is accepted.
+pub fn serialized_size<T: Serialize + Versionize + Named>(ct: &T) -> io::Result<usize> {
+    let size = safe_serialized_size(ct).map_err(io::Error::other)?;
+    usize::try_from(size).map_err(io::Error::other)
+}
+
+/// Total serialized size in bytes of a table of ciphertexts; any tfhe integer
+/// type is accepted.
+pub fn encrypted_table_bytes<T: Serialize + Versionize + Named>(table: &[T]) -> usize {
+    table.iter().map(serialized_bytes).sum()
+}
+
+/// Approximate in-memory footprint in bytes of a table of ciphertexts, for
+/// capacity planning: its [`encrypted_table_bytes`], which leave out the
+/// allocator's overhead.
+pub fn table_memory_report<T: Serialize + Versionize + Named>(table: &[T]) -> usize {
+    encrypted_table_bytes(table)
+}
+
+/// Serialized size in bytes of a table packed into a compressed list.
+pub fn compressed_table_bytes(list: &CompressedCiphertextList) -> usize {
+    serialized_bytes(list)
+}
+
+/// Serialized size in bytes of a table encrypted into a compact list.
+pub fn compact_table_bytes(list: &CompactCiphertextList) -> usize {
+    serialized_bytes(list)
+}
+
+fn serialized_bytes<T: Serialize + Versionize + Named>(value: &T) -> usize {
+    let size = safe_serialized_size(value).expect("tfhe values are serializable");
+    usize::try_from(size).expect("serialized size fits in usize")
+}
+
+pub(crate) fn write_versioned<T>(value: &T, writer: impl Write, size_limit: u64) -> io::Result<()>
+where
+    T: Serialize + Versionize + Named,
+{
+    safe_serialize(value, writer, size_limit).map_err(io::Error::other)
+}
+
+pub(crate) fn read_versioned<T>(reader: impl Read, size_limit: u64) -> io::Result<T>
+where
+    T: DeserializeOwned + Unversionize + Named,
+{
+    safe_deserialize(reader, size_limit).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestHarness;
+
+    #[test]
+    fn test_serialized_size() {
+        let harness = TestHarness::new();
+        let a = harness.encrypt(3);
+        let b = harness.encrypt(5);
+        let (sum, product) = harness.run(|| (&a + &b, &a * &b));
+
+        let size = serialized_size(&sum).unwrap();
+        assert!((1 << 10..1 << 20).contains(&size), "unexpected result size: {} bytes", size);
+        assert_eq!(serialized_size(&product).unwrap(), size, "result size depends on the value");
+
+        let mut bytes = Vec::new();
+        write_ciphertext(&sum, &mut bytes).unwrap();
+        assert_eq!(bytes.len(), size);
+    }
+
+    #[test]
+    fn test_table_memory_report() {
+        let harness = TestHarness::new();
+        let indices_bytes = table_memory_report(harness.indices());
+        let fibs_bytes = table_memory_report(harness.fibs());
+
+        // Both tables hold 25 FheUint16, whose size does not depend on the value.
+        assert!(indices_bytes > 0);
+        assert_eq!(indices_bytes, fibs_bytes);
+        assert_eq!(indices_bytes, crate::FIBONACCI_TABLE_LEN * serialized_size(&harness.fibs()[0]).unwrap());
+    }
+}
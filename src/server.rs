@@ -0,0 +1,123 @@
+//! Server-side operations, exchanged with the client as serialized bytes.
+//!
+//! The counterpart of [`client`](crate::client): the encrypted index comes in as
+//! bytes and the encrypted `F(n)` goes back as bytes, so the server only ever
+//! needs the server and public keys and never sees a plaintext. A long-running
+//! server can build its tables once with [`get_or_init_tables`].
+
+use crate::serialization::{read_ciphertext, write_ciphertext};
+use crate::{
+    build_encrypted_fibs, build_encrypted_indices, fibonacci_lookup_with_tables, with_server_key, FheUint16,
+    PublicKey, ServerKey,
+};
+use std::io::{self, Read, Write};
+use std::sync::OnceLock;
+
+/// Tables of [`get_or_init_tables`], built on first use.
+static TABLES: OnceLock<(Vec<FheUint16>, Vec<FheUint16>)> = OnceLock::new();
+
+/// The index and Fibonacci tables of the process, built from `public_key` by the
+/// first call and shared read-only by every later one, on any thread.
+///
+/// Meant for a long-running server answering many requests under one key pair:
+/// later calls return the tables of the first `public_key` whatever key they
+/// pass, so a server holding several key pairs must keep one set of tables per
+/// key instead. Calls racing the first one wait for its tables rather than
+/// building their own.
+pub fn get_or_init_tables(public_key: &PublicKey) -> (&'static [FheUint16], &'static [FheUint16]) {
+    let (encrypted_indices, encrypted_fibs) =
+        TABLES.get_or_init(|| (build_encrypted_indices(public_key), build_encrypted_fibs(public_key)));
+    (encrypted_indices, encrypted_fibs)
+}
+
+/// Read a serialized encrypted index from `input`, compute `F(n)` with the
+/// lookup strategy and write the serialized encrypted result to `output`.
+///
+/// The lookup tables are built from `public_key` on every call. Requires the
+/// server key to be set on the calling thread.
+pub fn lookup_ciphertext(input: impl Read, output: impl Write, public_key: &PublicKey) -> io::Result<()> {
+    let n = read_ciphertext(input)?;
+    let encrypted_indices = build_encrypted_indices(public_key);
+    let encrypted_fibs = build_encrypted_fibs(public_key);
+    let result = fibonacci_lookup_with_tables(&n, &encrypted_indices, &encrypted_fibs);
+    write_ciphertext(&result, output)
+}
+
+/// Server-only entry point: [`lookup_ciphertext`] with `server_key` set for the
+/// duration of the call.
+///
+/// Takes everything the server holds and nothing else, so code built on it
+/// cannot reach a client key: the result stays encrypted until the client
+/// decrypts it.
+pub fn serve_lookup(
+    input: impl Read,
+    output: impl Write,
+    server_key: ServerKey,
+    public_key: &PublicKey,
+) -> io::Result<()> {
+    with_server_key(server_key, || lookup_ciphertext(input, output, public_key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{decrypt_result, encrypt_index};
+    use crate::test_support::TestHarness;
+
+    #[test]
+    fn test_lookup_ciphertext_round_trip() {
+        let harness = TestHarness::new();
+        let request = encrypt_index(9, harness.pks());
+
+        let mut response = Vec::new();
+        harness.run(|| lookup_ciphertext(request.as_slice(), &mut response, harness.pks())).unwrap();
+
+        assert_eq!(decrypt_result(&response, harness.client_key()).unwrap(), 34);
+        assert!(harness.run(|| lookup_ciphertext(&request[..request.len() / 2], Vec::new(), harness.pks())).is_err());
+    }
+
+    #[test]
+    fn test_serve_lookup_without_client_key() {
+        let harness = TestHarness::new();
+        let request = encrypt_index(12, harness.pks());
+
+        // The server thread is only handed what the server holds: the request,
+        // the server key and the public key.
+        let (server_key, pks) = (harness.server_key().clone(), harness.pks());
+        let server = std::thread::spawn(move || {
+            let mut response = Vec::new();
+            serve_lookup(request.as_slice(), &mut response, server_key, pks).map(|()| response)
+        });
+        let response = server.join().unwrap().unwrap();
+
+        assert_eq!(decrypt_result(&response, harness.client_key()).unwrap(), 144);
+    }
+
+    #[test]
+    fn test_get_or_init_tables_shared_across_threads() {
+        let harness = TestHarness::new();
+        let pks = harness.pks();
+
+        let results: Vec<_> = std::thread::scope(|scope| {
+            let workers: Vec<_> = [3u16, 10, 17]
+                .into_iter()
+                .map(|n| {
+                    let encrypted = harness.encrypt(n);
+                    let server_key = harness.server_key().clone();
+                    scope.spawn(move || {
+                        let (encrypted_indices, encrypted_fibs) = get_or_init_tables(pks);
+                        with_server_key(server_key, || {
+                            fibonacci_lookup_with_tables(&encrypted, encrypted_indices, encrypted_fibs)
+                        })
+                    })
+                })
+                .collect();
+            workers.into_iter().map(|worker| worker.join().unwrap()).collect()
+        });
+
+        let decrypted: Vec<u16> = results.iter().map(|result| harness.decrypt(result)).collect();
+        assert_eq!(decrypted, [2, 55, 1597]);
+        // Every call sees the same tables.
+        assert!(std::ptr::eq(get_or_init_tables(pks).0, get_or_init_tables(pks).0));
+    }
+}
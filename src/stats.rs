@@ -0,0 +1,124 @@
+//! Homomorphic operation counts, with the `stats` feature.
+//!
+//! The strategies of [`STRATEGIES`](crate::STRATEGIES), their `u32`, array and
+//! saturating variants, the lookups of the other sequences and the queries built
+//! on the tables, such as [`fibonacci_next`](crate::fibonacci_next), count their
+//! `eq`, `select`, `add` and `mul` calls, e.g. 24 `eq` and 24 `select` for one
+//! [`fibonacci_lookup_with_tables`](crate::fibonacci_lookup_with_tables), so
+//! that op counts can be compared with measured times. Operations with a scalar
+//! operand are counted too. Counts are kept per thread: operations that the
+//! batch and tree lookups run on rayon workers are not seen by the calling
+//! thread.
+
+use crate::OpCounts;
+use std::cell::Cell;
+
+thread_local! {
+    /// Operations counted on this thread since it started.
+    static COUNTS: Cell<OpCounts> = const { Cell::new(OpCounts { eq: 0, select: 0, add: 0, mul: 0 }) };
+}
+
+pub(crate) fn count(update: impl FnOnce(&mut OpCounts)) {
+    let mut counts = COUNTS.get();
+    update(&mut counts);
+    COUNTS.set(counts);
+}
+
+/// Run `f` and return the operations it performed on the calling thread.
+pub fn record<T>(f: impl FnOnce() -> T) -> (T, OpCounts) {
+    let before = COUNTS.get();
+    let result = f();
+    let after = COUNTS.get();
+    let counts = OpCounts {
+        eq: after.eq - before.eq,
+        select: after.select - before.select,
+        add: after.add - before.add,
+        mul: after.mul - before.mul,
+    };
+    (result, counts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        build_trivial_fibs, build_trivial_indices, fibonacci_additions, fibonacci_fast_doubling,
+        fibonacci_index_of, fibonacci_lookup_scalar_index, fibonacci_lookup_with_tables, fibonacci_matrix_pow,
+        fibonacci_digit_sum, fibonacci_lookup_checked, fibonacci_next, fibonacci_prefix_sum,
+        fibonacci_sum_of_two,
+    };
+    use crate::estimate::estimate_ops;
+    use crate::test_support::TestHarness;
+    use crate::{StrategyChoice, MAX_FIBONACCI_INDEX};
+
+    #[test]
+    fn test_op_counts() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            // Counts do not depend on the values, trivial tables keep the setup cheap.
+            let encrypted_indices = build_trivial_indices();
+            let encrypted_fibs = build_trivial_fibs();
+            let n = harness.encrypt(7);
+
+            let (_, lookup) = record(|| fibonacci_lookup_with_tables(&n, &encrypted_indices, &encrypted_fibs));
+            assert_eq!(lookup, OpCounts { eq: 24, select: 24, add: 0, mul: 0 });
+
+            // fibonacci_next compares indices through the same helper as the lookup,
+            // fibonacci_index_of compares values: one eq per entry either way.
+            let (_, next) = record(|| fibonacci_next(&n, &encrypted_indices, &encrypted_fibs));
+            assert_eq!(next, OpCounts { eq: 24, select: 24, add: 0, mul: 0 });
+            let (_, index_of) = record(|| fibonacci_index_of(&n, &encrypted_indices, &encrypted_fibs));
+            assert_eq!(index_of, OpCounts { eq: 24, select: 24, add: 0, mul: 0 });
+
+            // Comparisons with plaintext indices count as `eq` too.
+            let (_, scalar_index) = record(|| fibonacci_lookup_scalar_index(&n, &encrypted_fibs));
+            assert_eq!(scalar_index, OpCounts { eq: 24, select: 24, add: 0, mul: 0 });
+
+            // Ordering comparisons count as `eq`, scalar operations as their
+            // ciphertext counterparts and divisions as `mul`.
+            let (_, checked) = record(|| fibonacci_lookup_checked(&n, &encrypted_indices, &encrypted_fibs));
+            assert_eq!(checked, OpCounts { eq: 25, select: 24, add: 0, mul: 0 });
+            let (_, prefix_sum) = record(|| fibonacci_prefix_sum(&n, &encrypted_indices, &encrypted_fibs));
+            assert_eq!(prefix_sum, OpCounts { eq: 24, select: 24, add: 2, mul: 0 });
+            let (_, digit_sum) = record(|| fibonacci_digit_sum(&n, &encrypted_indices, &encrypted_fibs));
+            assert_eq!(digit_sum, OpCounts { eq: 24, select: 24, add: 5, mul: 4 });
+
+            let m = harness.encrypt(5);
+            let (_, sum_of_two) = record(|| fibonacci_sum_of_two(&n, &m, &encrypted_indices, &encrypted_fibs));
+            assert_eq!(sum_of_two, OpCounts { eq: 48, select: 48, add: 1, mul: 0 });
+
+            let (_, additions) = record(|| fibonacci_additions(&n, harness.pks()));
+            assert_eq!(additions, OpCounts { eq: 24, select: 24, add: 23, mul: 0 });
+
+            // Five bits of n: per bit, both strategies do one eq extracting the
+            // bit, then fast doubling 4 mul, 2 add and 1 sub and the matrix
+            // strategy one or two 2x2 products of 8 mul and 4 add; both end with
+            // one eq and one select zeroing an n past the table.
+            let (_, fast_doubling) = record(|| fibonacci_fast_doubling(&n, harness.pks()));
+            assert_eq!(fast_doubling, OpCounts { eq: 6, select: 11, add: 15, mul: 20 });
+
+            let (_, matrix) = record(|| fibonacci_matrix_pow(&n, harness.pks()));
+            assert_eq!(matrix, OpCounts { eq: 6, select: 21, add: 36, mul: 72 });
+        });
+    }
+
+    #[test]
+    fn test_estimate_matches_counts() {
+        let harness = TestHarness::new();
+        harness.run(|| {
+            let encrypted_indices = build_trivial_indices();
+            let encrypted_fibs = build_trivial_fibs();
+            let n = harness.encrypt(7);
+
+            let (_, additions) = record(|| fibonacci_additions(&n, harness.pks()));
+            assert_eq!(additions, estimate_ops(StrategyChoice::Additions, MAX_FIBONACCI_INDEX));
+            let (_, lookup) = record(|| fibonacci_lookup_with_tables(&n, &encrypted_indices, &encrypted_fibs));
+            assert_eq!(lookup, estimate_ops(StrategyChoice::Lookup, MAX_FIBONACCI_INDEX));
+            // The bit extraction and the range check of the matrix strategy are
+            // estimated as one eq each.
+            let (_, matrix) = record(|| fibonacci_matrix_pow(&n, harness.pks()));
+            assert_eq!(matrix.eq, u64::from(u16::BITS - MAX_FIBONACCI_INDEX.leading_zeros()) + 1);
+            assert_eq!(matrix, estimate_ops(StrategyChoice::Matrix, MAX_FIBONACCI_INDEX));
+        });
+    }
+}
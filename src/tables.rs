@@ -0,0 +1,118 @@
+//! Caching the encrypted lookup tables on disk.
+//!
+//! The tables only depend on the public key, so the server can build them once
+//! and load them on startup instead of re-encrypting them every run. A table
+//! file is only meaningful with the keys it was built from.
+
+use crate::serialization::{read_ciphertext, write_ciphertext};
+use crate::{FheUint16, FIBONACCI_TABLE_LEN};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Path of the table file of the sequence named `name` in `dir`.
+pub fn tables_path(dir: impl AsRef<Path>, name: &str) -> PathBuf {
+    dir.as_ref().join(format!("{}_tables.bin", name))
+}
+
+/// Write an index table and its value table to `path`.
+///
+/// Each table is stored as its length followed by its ciphertexts.
+pub fn save_tables(path: impl AsRef<Path>, indices: &[FheUint16], values: &[FheUint16]) -> io::Result<()> {
+    let path = path.as_ref();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_table(indices, &mut writer)?;
+    write_table(values, &mut writer)?;
+    writer.flush()
+}
+
+/// Read the tables written by [`save_tables`].
+///
+/// The index table must have [`FIBONACCI_TABLE_LEN`] entries and the value
+/// table `values_len`, e.g. `Lucas::MAX_INDEX + 1`; any other length is an
+/// [`io::ErrorKind::InvalidData`] error, so a stale or truncated cache cannot make
+/// a lookup index past the end of a table.
+pub fn load_tables(path: impl AsRef<Path>, values_len: usize) -> io::Result<(Vec<FheUint16>, Vec<FheUint16>)> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let indices = read_table(&mut reader)?;
+    let values = read_table(&mut reader)?;
+    check_len("index", indices.len(), FIBONACCI_TABLE_LEN)?;
+    check_len("value", values.len(), values_len)?;
+    Ok((indices, values))
+}
+
+/// Load the tables from `path` if present, otherwise build them with `build` and
+/// save them there; `values_len` is checked as in [`load_tables`].
+pub fn load_or_build_tables(
+    path: impl AsRef<Path>,
+    values_len: usize,
+    build: impl FnOnce() -> (Vec<FheUint16>, Vec<FheUint16>),
+) -> io::Result<(Vec<FheUint16>, Vec<FheUint16>)> {
+    let path = path.as_ref();
+    if path.is_file() {
+        return load_tables(path, values_len);
+    }
+    let (indices, values) = build();
+    save_tables(path, &indices, &values)?;
+    Ok((indices, values))
+}
+
+fn write_table(table: &[FheUint16], mut writer: impl Write) -> io::Result<()> {
+    writer.write_all(&(table.len() as u64).to_le_bytes())?;
+    table.iter().try_for_each(|ct| write_ciphertext(ct, &mut writer))
+}
+
+fn check_len(table: &str, len: usize, expected: usize) -> io::Result<()> {
+    if len == expected {
+        return Ok(());
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("the cached {} table has {} entries, expected {}", table, len, expected),
+    ))
+}
+
+fn read_table(mut reader: impl Read) -> io::Result<Vec<FheUint16>> {
+    let mut len = [0u8; 8];
+    reader.read_exact(&mut len)?;
+    (0..u64::from_le_bytes(len)).map(|_| read_ciphertext(&mut reader)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestHarness;
+    use crate::{fibonacci_lookup_with_tables, fibonacci_plaintext};
+
+    #[test]
+    fn test_tables_round_trip() {
+        let dir = std::env::temp_dir().join(format!("fib-fhe-tables-{}", std::process::id()));
+        let harness = TestHarness::new();
+        let (encrypted_indices, encrypted_fibs) = (harness.indices(), harness.fibs());
+        let path = tables_path(&dir, "fib");
+        save_tables(&path, encrypted_indices, encrypted_fibs).unwrap();
+        let (loaded_indices, loaded_fibs) =
+            load_or_build_tables(&path, FIBONACCI_TABLE_LEN, || unreachable!("tables were saved")).unwrap();
+        let wrong_len = load_tables(&path, FIBONACCI_TABLE_LEN + 1).map(|_| ());
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(wrong_len.unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(loaded_indices.len(), encrypted_indices.len());
+        assert_eq!(loaded_fibs.len(), encrypted_fibs.len());
+
+        let encrypted = harness.encrypt(7);
+        let (enc_fresh, enc_loaded) = harness.run(|| {
+            (
+                fibonacci_lookup_with_tables(&encrypted, encrypted_indices, encrypted_fibs),
+                fibonacci_lookup_with_tables(&encrypted, &loaded_indices, &loaded_fibs),
+            )
+        });
+        let dec_fresh: u16 = harness.decrypt(&enc_fresh);
+        let dec_loaded: u16 = harness.decrypt(&enc_loaded);
+
+        assert_eq!(dec_loaded, dec_fresh);
+        assert_eq!(dec_loaded, fibonacci_plaintext(7));
+    }
+}
@@ -0,0 +1,97 @@
+//! Keys and lookup tables shared by the tests of every module.
+//!
+//! Key generation dominates the cost of a test: [`TestHarness::new`] hands out
+//! keys generated once per test binary. Tests that need other parameters, or
+//! keys that went through a round trip, wrap them in their own [`Fixture`].
+
+use std::sync::OnceLock;
+use tfhe::prelude::*;
+use tfhe::{generate_keys, ClientKey, Config, ConfigBuilder, FheUint16, PublicKey, ServerKey};
+
+use crate::keys::Keys;
+use crate::{build_encrypted_fibs, build_encrypted_indices, with_server_key};
+
+/// A set of keys and the Fibonacci lookup tables encrypted with them. The
+/// tables are only built by the first test that asks for them.
+pub(crate) struct Fixture {
+    keys: Keys,
+    tables: OnceLock<(Vec<FheUint16>, Vec<FheUint16>)>,
+}
+
+impl Fixture {
+    /// Fresh keys for `config`.
+    pub(crate) fn new(config: Config) -> Self {
+        let (client_key, server_key) = generate_keys(config);
+        let public_key = PublicKey::new(&client_key);
+        Self::from_keys(Keys { client_key, server_key, public_key })
+    }
+
+    pub(crate) fn from_keys(keys: impl Into<Keys>) -> Self {
+        Self { keys: keys.into(), tables: OnceLock::new() }
+    }
+
+    pub(crate) fn harness(&self) -> TestHarness<'_> {
+        TestHarness { fixture: self }
+    }
+}
+
+/// A [`Fixture`] behind encryption, decryption and table accessors, so a test
+/// is down to its assertions. Tests set the server key with [`TestHarness::run`],
+/// so it is scoped to the test's closure.
+pub(crate) struct TestHarness<'a> {
+    fixture: &'a Fixture,
+}
+
+impl TestHarness<'static> {
+    /// A harness over the default-parameter keys every test shares.
+    pub(crate) fn new() -> Self {
+        static FIXTURE: OnceLock<Fixture> = OnceLock::new();
+        FIXTURE.get_or_init(|| Fixture::new(ConfigBuilder::default().build())).harness()
+    }
+}
+
+impl<'a> TestHarness<'a> {
+    pub(crate) fn encrypt(&self, n: u16) -> FheUint16 {
+        FheUint16::encrypt(n, &self.fixture.keys.client_key)
+    }
+
+    /// Decrypt any ciphertext, e.g. a `FheUint16` to `u16` or a `FheBool` to
+    /// `bool`.
+    pub(crate) fn decrypt<T>(&self, ct: &impl FheDecrypt<T>) -> T {
+        ct.decrypt(&self.fixture.keys.client_key)
+    }
+
+    pub(crate) fn keys(&self) -> &'a Keys {
+        &self.fixture.keys
+    }
+
+    pub(crate) fn client_key(&self) -> &'a ClientKey {
+        &self.fixture.keys.client_key
+    }
+
+    pub(crate) fn server_key(&self) -> &'a ServerKey {
+        &self.fixture.keys.server_key
+    }
+
+    pub(crate) fn pks(&self) -> &'a PublicKey {
+        &self.fixture.keys.public_key
+    }
+
+    pub(crate) fn indices(&self) -> &'a [FheUint16] {
+        &self.tables().0
+    }
+
+    pub(crate) fn fibs(&self) -> &'a [FheUint16] {
+        &self.tables().1
+    }
+
+    /// Run `f` with the fixture's server key set, see [`with_server_key`].
+    pub(crate) fn run<T>(&self, f: impl FnOnce() -> T) -> T {
+        with_server_key(self.fixture.keys.server_key.clone(), f)
+    }
+
+    fn tables(&self) -> &'a (Vec<FheUint16>, Vec<FheUint16>) {
+        let pks = self.pks();
+        self.fixture.tables.get_or_init(|| (build_encrypted_indices(pks), build_encrypted_fibs(pks)))
+    }
+}
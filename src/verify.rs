@@ -0,0 +1,175 @@
+//! Checking encrypted results against the plaintext reference.
+
+use crate::{
+    build_encrypted_fibs, build_encrypted_indices, fibonacci_lookup_with_tables,
+    fibonacci_plaintext, with_server_key, FheUint16, STRATEGIES,
+};
+use std::error::Error;
+use std::fmt;
+use tfhe::prelude::*;
+use tfhe::{ClientKey, PublicKey, ServerKey};
+
+/// Decrypted result of one strategy, compared with [`fibonacci_plaintext`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VerifiedResult {
+    /// Name of the strategy that produced `value`.
+    pub strategy: &'static str,
+    pub value: u16,
+    pub matches_plaintext: bool,
+}
+
+/// Encrypt `clear_n`, compute `F(n)` with the lookup strategy and check the
+/// decrypted value against the plaintext reference.
+///
+/// `server_key` is set with [`with_server_key`] for the duration of the call.
+pub fn compute_and_verify(
+    clear_n: u16,
+    client_key: &ClientKey,
+    public_key: &PublicKey,
+    server_key: &ServerKey,
+) -> VerifiedResult {
+    let n = FheUint16::encrypt(clear_n, client_key);
+
+    let result = with_server_key(server_key.clone(), || {
+        let encrypted_indices = build_encrypted_indices(public_key);
+        let encrypted_fibs = build_encrypted_fibs(public_key);
+        fibonacci_lookup_with_tables(&n, &encrypted_indices, &encrypted_fibs)
+    });
+
+    let value: u16 = result.decrypt(client_key);
+    VerifiedResult {
+        strategy: "lookup",
+        value,
+        matches_plaintext: value == fibonacci_plaintext(clear_n),
+    }
+}
+
+/// A strategy whose decrypted result differs from the plaintext reference.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StrategyMismatch {
+    /// Index the strategies were run for.
+    pub n: u16,
+    /// Name of the strategy in [`STRATEGIES`].
+    pub strategy: &'static str,
+    /// `F(n)` from [`fibonacci_plaintext`].
+    pub expected: u16,
+    /// Decrypted result of `strategy`.
+    pub actual: u16,
+}
+
+impl fmt::Display for StrategyMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "F({}) with {} is {}, expected {}", self.n, self.strategy, self.actual, self.expected)
+    }
+}
+
+impl Error for StrategyMismatch {}
+
+/// Check every strategy of [`STRATEGIES`] against the plaintext reference for
+/// `clear_n`, e.g. as a health check of a parameter set at startup.
+///
+/// Builds the lookup tables with `public_key` and returns the first strategy
+/// that disagrees. Requires the server key to be set on the calling thread.
+pub fn verify_strategies(
+    clear_n: u16,
+    client_key: &ClientKey,
+    public_key: &PublicKey,
+) -> Result<(), StrategyMismatch> {
+    let encrypted_indices = build_encrypted_indices(public_key);
+    let encrypted_fibs = build_encrypted_fibs(public_key);
+    check_strategies_with_tables(clear_n, client_key, public_key, &encrypted_indices, &encrypted_fibs)
+}
+
+/// [`verify_strategies`] over lookup tables built once by the caller, as in
+/// [`verify_strategies_with_tables`]: the first strategy that disagrees with
+/// the plaintext reference for `clear_n`, if any.
+///
+/// Requires the server key to be set on the calling thread.
+pub fn check_strategies_with_tables(
+    clear_n: u16,
+    client_key: &ClientKey,
+    public_key: &PublicKey,
+    encrypted_indices: &[FheUint16],
+    encrypted_fibs: &[FheUint16],
+) -> Result<(), StrategyMismatch> {
+    let results = verify_strategies_with_tables(clear_n, client_key, public_key, encrypted_indices, encrypted_fibs);
+    match results.into_iter().find(|result| !result.matches_plaintext) {
+        Some(result) => Err(StrategyMismatch {
+            n: clear_n,
+            strategy: result.strategy,
+            expected: fibonacci_plaintext(clear_n),
+            actual: result.value,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Compute `F(n)` with every strategy of [`STRATEGIES`] for the encrypted
+/// `clear_n` and check each decrypted value against the plaintext reference.
+///
+/// `encrypted_indices` and `encrypted_fibs` are the lookup tables, built once
+/// by the caller and reused across indices. Requires the server key to be set on
+/// the calling thread.
+pub fn verify_strategies_with_tables(
+    clear_n: u16,
+    client_key: &ClientKey,
+    public_key: &PublicKey,
+    encrypted_indices: &[FheUint16],
+    encrypted_fibs: &[FheUint16],
+) -> Vec<VerifiedResult> {
+    let n = FheUint16::encrypt(clear_n, client_key);
+    let expected = fibonacci_plaintext(clear_n);
+    STRATEGIES
+        .iter()
+        .map(|(strategy, compute)| {
+            let value: u16 = compute(&n, public_key, encrypted_indices, encrypted_fibs).decrypt(client_key);
+            VerifiedResult { strategy, value, matches_plaintext: value == expected }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestHarness;
+
+    #[test]
+    fn test_compute_and_verify() {
+        let harness = TestHarness::new();
+        let verified = compute_and_verify(10, harness.client_key(), harness.pks(), harness.server_key());
+
+        assert_eq!(
+            verified,
+            VerifiedResult { strategy: "lookup", value: 55, matches_plaintext: true }
+        );
+        // The key was scoped to the call: none is left on this thread.
+        assert!(std::panic::catch_unwind(|| FheUint16::encrypt_trivial(0u16)).is_err());
+    }
+
+    #[test]
+    fn test_verify_strategies_with_tables() {
+        let harness = TestHarness::new();
+        let verified = harness.run(|| {
+            verify_strategies_with_tables(7, harness.client_key(), harness.pks(), harness.indices(), harness.fibs())
+        });
+
+        let strategies: Vec<_> = verified.iter().map(|result| result.strategy).collect();
+        assert_eq!(strategies, STRATEGIES.iter().map(|(name, _)| *name).collect::<Vec<_>>());
+        for result in verified {
+            assert_eq!(result.value, 13, "{} mismatch for n = 7", result.strategy);
+            assert!(result.matches_plaintext);
+        }
+    }
+
+    #[test]
+    fn test_verify_strategies() {
+        let harness = TestHarness::new();
+        assert_eq!(harness.run(|| verify_strategies(11, harness.client_key(), harness.pks())), Ok(()));
+    }
+
+    #[test]
+    fn test_strategy_mismatch_display() {
+        let mismatch = StrategyMismatch { n: 10, strategy: "lookup", expected: 55, actual: 0 };
+        assert_eq!(mismatch.to_string(), "F(10) with lookup is 0, expected 55");
+    }
+}
@@ -0,0 +1,32 @@
+//! `fib-fhe --log-csv`: one row appended per run, under a single header.
+
+use std::fs;
+use std::process::Command;
+
+#[test]
+fn test_log_csv_appends_rows() {
+    let dir = std::env::temp_dir().join(format!("fib-fhe-log-csv-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("runs.csv");
+    let _ = fs::remove_file(&path);
+
+    // --plaintext keeps the runs instant; the rows carry no strategy results.
+    for n in ["10", "12"] {
+        let output = Command::new(env!("CARGO_BIN_EXE_fib-fhe"))
+            .args(["--plaintext", "--log-csv"])
+            .arg(&path)
+            .arg(n)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    }
+
+    let contents = fs::read_to_string(&path).unwrap();
+    let lines: Vec<_> = contents.lines().collect();
+    assert_eq!(lines.len(), 3, "{}", contents);
+    assert_eq!(lines[0], fib_fhe::csv_log::CSV_HEADER);
+    assert!(lines[1].ends_with(",10,55,,,,"), "{}", lines[1]);
+    assert!(lines[2].ends_with(",12,144,,,,"), "{}", lines[2]);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
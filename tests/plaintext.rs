@@ -0,0 +1,16 @@
+//! `fib-fhe --plaintext`: the reference value, printed without generating keys.
+
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+#[test]
+fn test_plaintext_flag() {
+    let start = Instant::now();
+    let output = Command::new(env!("CARGO_BIN_EXE_fib-fhe")).args(["--plaintext", "12"]).output().unwrap();
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "144\n");
+    // A loose bound, far below what key generation alone takes, so that a loaded
+    // machine does not make it flaky.
+    assert!(start.elapsed() < Duration::from_secs(30), "took {:?}", start.elapsed());
+}
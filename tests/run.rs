@@ -0,0 +1,19 @@
+//! End-to-end wiring of the default flow: key generation, encryption, every
+//! strategy the CLI runs by default and decryption, checked against the
+//! plaintext reference.
+
+use fib_fhe::run::run;
+use fib_fhe::{fibonacci_plaintext, MAX_FIBONACCI_INDEX};
+
+#[test]
+fn test_run_matches_plaintext() {
+    for clear_a in [7, MAX_FIBONACCI_INDEX] {
+        let results = run(clear_a);
+
+        let expected = fibonacci_plaintext(clear_a);
+        assert_eq!(results.expected, expected);
+        assert_eq!(results.additions, Some(expected), "additions mismatch for n = {}", clear_a);
+        assert_eq!(results.lookup, Some(expected), "lookup mismatch for n = {}", clear_a);
+        assert_eq!(results.matrix, Some(expected), "matrix mismatch for n = {}", clear_a);
+    }
+}